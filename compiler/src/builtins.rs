@@ -26,6 +26,7 @@ use std::io::stdout;
 use std::io::Write;
 
 use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
 
 // BUILT IN STD
 use include_dir::{Dir, File};
@@ -100,9 +101,308 @@ pub fn context_trigger(context: &Context, uid_counter: &mut usize) -> GdObj {
         func_id: context.func_id,
         mode: ObjectMode::Trigger,
         unique_id: *uid_counter,
+        output: context.output_level,
     }
 }
 
+// adds a trigger to a new trigger function, or one made by the same builtin
+fn push_fn_trigger(
+    mut params: AHashMap<u16, ObjParam>,
+    group: Group,
+    func_id: usize,
+    output: usize,
+    globals: &mut Globals,
+) {
+    params.insert(57, ObjParam::Group(group));
+    globals.uid_counter += 1;
+    globals.trigger_order += 1.0;
+    let obj = GdObj {
+        params,
+        func_id,
+        mode: ObjectMode::Trigger,
+        unique_id: globals.uid_counter,
+        output,
+    };
+    globals.func_ids[func_id]
+        .obj_list
+        .push((obj, TriggerOrder(globals.trigger_order)));
+}
+
+fn next_fn(parent: usize, globals: &mut Globals) -> (Group, usize) {
+    globals.func_ids.push(FunctionId {
+        parent: Some(parent),
+        obj_list: Vec::new(),
+        width: None,
+    });
+    (
+        Group::next_free(&mut globals.closed_groups),
+        globals.func_ids.len() - 1,
+    )
+}
+
+fn instant_count(item: Item, comparison: u8, count: f64, target: Group) -> AHashMap<u16, ObjParam> {
+    let mut params = AHashMap::default();
+    params.insert(1, ObjParam::Number(1811.0));
+    params.insert(80, ObjParam::Item(item));
+    params.insert(77, ObjParam::Number(count));
+    params.insert(88, ObjParam::Number(comparison as f64));
+    params.insert(51, ObjParam::Group(target));
+    params.insert(56, ObjParam::Bool(true));
+    params
+}
+
+fn pickup(item: Item, count: f64) -> AHashMap<u16, ObjParam> {
+    let mut params = AHashMap::default();
+    params.insert(1, ObjParam::Number(1817.0));
+    params.insert(80, ObjParam::Item(item));
+    params.insert(77, ObjParam::Number(count));
+    params
+}
+
+// how many triggers a trigger function (or group) made: the triggers of the function IDs that
+// only have triggers in the group, and of every function ID made inside them
+fn group_trigger_count(group: Group, globals: &Globals) -> usize {
+    let in_group = |obj: &GdObj| match obj.params.get(&57) {
+        Some(ObjParam::Group(g)) => *g == group,
+        Some(ObjParam::GroupList(l)) => l.contains(&group),
+        _ => false,
+    };
+    let mut counted = vec![false; globals.func_ids.len()];
+    let mut count = 0;
+    for (i, f) in globals.func_ids.iter().enumerate() {
+        // parents always come before their children
+        let parent_counted = f.parent.map(|p| counted[p]).unwrap_or(false);
+        if parent_counted
+            || (!f.obj_list.is_empty() && f.obj_list.iter().all(|(obj, _)| in_group(obj)))
+        {
+            counted[i] = true;
+            count += f.obj_list.len();
+        }
+    }
+    count
+}
+
+// binary search over the value of an item, activating leaves[i] when it's first + i
+// returns the group that starts the search and how many triggers it took
+fn item_search_tree(
+    item: Item,
+    leaves: &[Group],
+    first: usize,
+    parent: usize,
+    output: usize,
+    globals: &mut Globals,
+) -> (Group, usize) {
+    if leaves.len() == 1 {
+        return (leaves[0], 0);
+    }
+    let (group, func_id) = next_fn(parent, globals);
+
+    let mid = leaves.len() / 2;
+    let (upper, upper_cost) = item_search_tree(item, &leaves[mid..], first + mid, func_id, output, globals);
+    let (lower, lower_cost) = item_search_tree(item, &leaves[..mid], first, func_id, output, globals);
+
+    // smaller than mid goes down, larger than mid - 1 goes up. the lower half is checked first,
+    // since a leaf can lower the item right away, and then it would pass the other check too
+    push_fn_trigger(instant_count(item, 2, (first + mid) as f64, lower), group, func_id, output, globals);
+    push_fn_trigger(instant_count(item, 1, (first + mid - 1) as f64, upper), group, func_id, output, globals);
+    (group, upper_cost + lower_cost + 2)
+}
+
+// only the allowed builtins, so it's the same no matter how the map was built
+impl Hash for BuiltinPermissions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut allowed = self
+            .builtins
+            .iter()
+            .filter(|(_, allowed)| **allowed)
+            .map(|(b, _)| String::from(*b))
+            .collect::<Vec<_>>();
+        allowed.sort();
+        allowed.hash(state);
+        self.read_roots.hash(state);
+        self.write_roots.hash(state);
+        for (package, granted) in &self.packages {
+            package.hash(state);
+            granted.hash(state);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileAccess {
+    Read,
+    Write,
+}
+
+impl FileAccess {
+    pub fn of(b: Builtin) -> Option<Self> {
+        match b {
+            Builtin::ReadFile
+            | Builtin::FileExists
+            | Builtin::FileKind
+            | Builtin::MetaData
+            | Builtin::ReadDir => Some(FileAccess::Read),
+            Builtin::WriteFile
+            | Builtin::DeleteFile
+            | Builtin::MkDir
+            | Builtin::RmDir
+            | Builtin::RmDirAll => Some(FileAccess::Write),
+            _ => None,
+        }
+    }
+
+    fn builtins(self) -> &'static [Builtin] {
+        match self {
+            FileAccess::Read => &[
+                Builtin::ReadFile,
+                Builtin::FileExists,
+                Builtin::FileKind,
+                Builtin::MetaData,
+                Builtin::ReadDir,
+            ],
+            FileAccess::Write => &[
+                Builtin::WriteFile,
+                Builtin::DeleteFile,
+                Builtin::MkDir,
+                Builtin::RmDir,
+                Builtin::RmDirAll,
+            ],
+        }
+    }
+
+    fn flag(self) -> &'static str {
+        match self {
+            FileAccess::Read => "--allow-read",
+            FileAccess::Write => "--allow-write",
+        }
+    }
+}
+
+// the absolute path with symlinks and `..` resolved, also for paths that don't exist yet
+// (like a file that is about to be written)
+pub fn resolve_path(path: &Path) -> std::io::Result<PathBuf> {
+    let path = env::current_dir()?.join(path);
+    let mut missing = Vec::new();
+    let mut existing = path.as_path();
+    let mut resolved = loop {
+        match existing.canonicalize() {
+            Ok(p) => break p,
+            Err(e) => match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    missing.push(name.to_owned());
+                    existing = parent;
+                }
+                // `..` at the end of a missing path
+                (Some(parent), None) => {
+                    missing.push(std::ffi::OsString::from(".."));
+                    existing = parent;
+                }
+                _ => return Err(e),
+            },
+        }
+    };
+    // the missing part can't contain symlinks, so it's fine to resolve it by hand
+    for part in missing.into_iter().rev() {
+        if part == ".." {
+            resolved.pop();
+        } else if part != "." {
+            resolved.push(part);
+        }
+    }
+    Ok(resolved)
+}
+
+impl BuiltinPermissions {
+    // allows the read or write builtins, but only inside `root`
+    pub fn allow_path(&mut self, access: FileAccess, root: &Path) -> std::io::Result<()> {
+        let root = resolve_path(root)?;
+        for b in access.builtins() {
+            self.set(*b, true);
+        }
+        let roots = match access {
+            FileAccess::Read => &mut self.read_roots,
+            FileAccess::Write => &mut self.write_roots,
+        };
+        roots.get_or_insert_with(Vec::new).push(root);
+        Ok(())
+    }
+
+    // the package can only use the unsafe builtins it was granted, no matter what
+    // the script itself is allowed to use
+    pub fn grant_package(&mut self, package: String, builtins: Vec<Builtin>) {
+        let mut builtins = builtins;
+        builtins.sort_by_key(|b| String::from(*b));
+        builtins.dedup();
+        self.packages.insert(package, builtins);
+    }
+
+    // `chain` is every library the call was made through (empty for the script). When
+    // there are packages in it, the builtin has to be granted to all of them
+    pub fn is_allowed_in(&self, b: Builtin, chain: &[String]) -> bool {
+        if self.is_safe(b) || !chain.iter().any(|m| self.packages.contains_key(m)) {
+            self.is_allowed(b)
+        } else {
+            self.package_without(b, chain).is_none()
+        }
+    }
+
+    // the first package in the chain that wasn't granted the builtin
+    pub fn package_without<'a>(&self, b: Builtin, chain: &'a [String]) -> Option<&'a String> {
+        if self.is_safe(b) {
+            return None;
+        }
+        chain
+            .iter()
+            .find(|m| matches!(self.packages.get(*m), Some(granted) if !granted.contains(&b)))
+    }
+
+    pub fn check_path(&self, b: Builtin, path: &str) -> Result<(), String> {
+        let access = match FileAccess::of(b) {
+            Some(a) => a,
+            None => return Ok(()),
+        };
+        let roots = match access {
+            FileAccess::Read => &self.read_roots,
+            FileAccess::Write => &self.write_roots,
+        };
+        let roots = match roots {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+        let resolved = resolve_path(Path::new(path))
+            .map_err(|e| format!("Could not resolve the path \"{}\": {}", path, e))?;
+        if roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Access to \"{}\" ({}) was denied, because it is not inside any of the directories allowed with `{}`: {}",
+                path,
+                resolved.display(),
+                access.flag(),
+                roots
+                    .iter()
+                    .map(|r| format!("\"{}\"", r.display()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        }
+    }
+}
+
+// builtins that affect or read something outside of the compiler, or give a different result every time
+fn has_side_effects(b: Builtin, permissions: &BuiltinPermissions) -> bool {
+    !permissions.is_safe(b)
+        || matches!(
+            b,
+            Builtin::Time
+                | Builtin::GetInput
+                | Builtin::Random
+                | Builtin::ReadLevel
+                | Builtin::CWD
+                | Builtin::OutputLevel
+        )
+}
+
 pub type ArbitraryId = u16;
 pub type SpecificId = u16;
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -294,6 +594,22 @@ impl Value {
             };
 
             match self {
+                Value::Builtins if member.as_ref() == "defines" => {
+                    let defines = globals
+                        .defines
+                        .clone()
+                        .into_iter()
+                        .map(|(name, value)| {
+                            (name, store_const_value(value, globals, context.start_group, info.position))
+                        })
+                        .collect();
+                    Some(store_const_value(
+                        Value::Dict(defines),
+                        globals,
+                        context.start_group,
+                        info.position,
+                    ))
+                }
                 Value::Builtins => match Builtin::from_str(member.as_str()) {
                     Err(_) => None,
                     Ok(builtin) => Some(store_const_value(
@@ -479,7 +795,14 @@ macro_rules! builtins {
         ];
 
         #[derive(Debug, Clone)]
-        pub struct BuiltinPermissions (AHashMap<Builtin, bool>);
+        pub struct BuiltinPermissions {
+            builtins: AHashMap<Builtin, bool>,
+            // directories the file builtins are limited to (None means anywhere)
+            read_roots: Option<Vec<PathBuf>>,
+            write_roots: Option<Vec<PathBuf>>,
+            // unsafe builtins each pckp package was approved to use, by library name
+            packages: BTreeMap<String, Vec<Builtin>>,
+        }
 
         impl BuiltinPermissions {
             pub fn new() -> Self {
@@ -487,13 +810,18 @@ macro_rules! builtins {
                 $(
                     map.insert(Builtin::$variant, $safe);
                 )*
-                Self(map)
+                Self {
+                    builtins: map,
+                    read_roots: None,
+                    write_roots: None,
+                    packages: BTreeMap::new(),
+                }
             }
             pub fn is_allowed(&self, b: Builtin) -> bool {
-                self.0[&b]
+                self.builtins[&b]
             }
             pub fn set(&mut self, b: Builtin, setting: bool) {
-                self.0.insert(b, setting);
+                self.builtins.insert(b, setting);
             }
             pub fn is_safe(&self, b: Builtin) -> bool {
                 match b {
@@ -545,11 +873,24 @@ macro_rules! builtins {
             #![allow(unused_variables)]
             #![allow(unused_mut)]
             #![allow(unused_parens)]
-            if !$globals.permissions.is_allowed(func) {
-                if !$globals.permissions.is_safe(func) {
+            if !$globals.permissions.is_allowed_in(func, &$info.module_chain) {
+                if let Some(package) = $globals.permissions.package_without(func, &$info.module_chain) {
                     return Err(RuntimeError::BuiltinError {
                         builtin: String::from(func),
-                        message: format!("This built-in function requires an explicit `--allow {}` flag when running the script", String::from(func)),
+                        message: format!(
+                            "The package `{}` was not given permission to use this built-in function. It has to be listed under `permissions` in the package's pckp.yaml, and approved with `allow` in its entry under `dependencies` in your pckp.yaml",
+                            package
+                        ),
+                        $info,
+                    })
+                } else if !$globals.permissions.is_safe(func) {
+                    let scoped = match FileAccess::of(func) {
+                        Some(access) => format!(" (or `{} <DIR>` to only allow it inside a directory)", access.flag()),
+                        None => String::new(),
+                    };
+                    return Err(RuntimeError::BuiltinError {
+                        builtin: String::from(func),
+                        message: format!("This built-in function requires an explicit `--allow {}` flag when running the script{}", String::from(func), scoped),
                         $info,
                     })
                 } else {
@@ -560,6 +901,19 @@ macro_rules! builtins {
                     })
                 }
             }
+            // every file builtin takes the path as its first argument
+            if let Some(Value::Str(path)) = $arguments.first().map(|a| &$globals.stored_values[*a]) {
+                if let Err(message) = $globals.permissions.check_path(func, path) {
+                    return Err(RuntimeError::BuiltinError {
+                        builtin: String::from(func),
+                        message,
+                        $info,
+                    })
+                }
+            }
+            if has_side_effects(func, &$globals.permissions) {
+                $globals.side_effects += 1;
+            }
             for full_context in contexts.iter() {
                 let $full_context: *mut FullContext = full_context;
                 let $context = full_context.inner();
@@ -698,6 +1052,19 @@ macro_rules! builtins {
                 }
             }
 
+            // `$.defines` is a value, not a function, but it's used like the builtins
+            normal_ones.push(("defines", String::from(concat!(
+                "## $.defines\n",
+                "> ## Description:\n",
+                "> A dictionary with the values given with `-D name=value` when building. Numbers and `true`/`false` become numbers and booleans, and everything else (or anything in quotes) is a string<div>\n",
+                "> ## Example:\n",
+                "> ```spwn\n",
+                "> // spwn build level.spwn -D difficulty=2 -D name=\"test\"\n",
+                "> difficulty = $.defines.get(\"difficulty\", 1)\n",
+                "> ```\n",
+                "> **Allowed by default:** true\n",
+            ))));
+
             normal_ones.sort_by(|a, b| a.0.cmp(&b.0));
             operators.sort_by(|a, b| a.0.cmp(&b.0));
 
@@ -758,6 +1125,8 @@ builtins! {
 
         }
         writeln!(globals.std_out, "{}", out).expect("Error writing to output");
+        globals.printed += &out;
+        globals.printed.push('\n');
         Value::Null
     }
 
@@ -792,13 +1161,49 @@ builtins! {
         Value::Str(env!("CARGO_PKG_VERSION").to_string())
     }
 
-    [GetInput] #[safe = true, desc = "Gets some input from the user", example = "// inp = $.get_input('What is your name?')"]
-    fn get_input((prompt): Str) {
-        print!("{}", prompt);
-        stdout()
-            .flush()
-            .expect("Unexpected error occurred when trying to get user input");
-        Value::Str(text_io::read!("{}\n"))
+    [GetInput] #[safe = true, desc = "Gets some input from the user. The input can also be given when building with `--input key=value` or an inputs file, where the key is the optional second argument or else the prompt", example = "// inp = $.get_input('What is your name?', 'name')"]
+    fn get_input(#["The prompt, and optionally a key for giving the input with `--input`"]) {
+        if arguments.is_empty() || arguments.len() > 2 {
+            return Err(RuntimeError::BuiltinError {
+                builtin,
+                message: String::from("Expected 1 or 2 arguments, the prompt and optionally the input key"),
+                info,
+            });
+        }
+        let mut strings = Vec::new();
+        for (i, arg) in arguments.iter().enumerate() {
+            match &globals.stored_values[*arg] {
+                Value::Str(s) => strings.push(s.clone()),
+                _ => {
+                    return Err(RuntimeError::BuiltinError {
+                        builtin,
+                        message: format!("Expected a string for argument {}, found type {}", i + 1, globals.get_type_str(*arg)),
+                        info,
+                    });
+                }
+            }
+        }
+        let prompt = &strings[0];
+        let key = strings.get(1).map(|k| k.as_str()).unwrap_or_else(|| prompt.trim());
+
+        if let Some(input) = globals.inputs.get(key) {
+            Value::Str(input.clone())
+        } else {
+            // reading from something that isn't a terminal would wait forever or read garbage
+            #[cfg(not(target_arch = "wasm32"))]
+            if !std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+                return Err(RuntimeError::BuiltinError {
+                    builtin,
+                    message: format!("No input was given for \"{}\" (use `--input \"{}=...\"` or an inputs file), and the input can't be asked for because stdin is not a terminal", key, key),
+                    info,
+                });
+            }
+            print!("{}", prompt);
+            stdout()
+                .flush()
+                .expect("Unexpected error occurred when trying to get user input");
+            Value::Str(text_io::read!("{}\n"))
+        }
     }
 
 
@@ -826,79 +1231,119 @@ builtins! {
 
 
 
-    [HTTPRequest] #[safe = false, desc = "Sends an HTTP request", example = ""] fn http_request((method): Str, (url): Str, (headers): Dict, (body): Str) {
+    [HTTPRequest] #[safe = false, desc = "Sends an HTTP request (or answers it with a recorded response when building with `--http-fixtures`)", example = ""] fn http_request((method): Str, (url): Str, (headers): Dict, (body): Str) {
         #[cfg(not(target_arch = "wasm32"))]
         {
-            let mut headermap = reqwest::header::HeaderMap::new();
-            for (name, value) in &headers {
-                let header_name = match reqwest::header::HeaderName::from_bytes(name.as_bytes()) {
-                    Ok(hname) => hname,
-                    Err(_) => {
+            let fixtures = globals.http_fixtures.clone();
+            let response = match &fixtures {
+                Some(f) if !f.record => match f.load(&method, &url) {
+                    Ok(r) => r,
+                    Err(message) => {
                         return Err(RuntimeError::BuiltinError {
                             builtin,
-                            message: format!("Could not convert header name: '{}'", name),
+                            message,
                             info
                         })
                     }
-                };
-                let header_value = globals.stored_values[*value].clone().to_str(globals);
-                headermap.insert(header_name, header_value.trim_matches('\'').parse().unwrap());
-            }
-
-            let client = reqwest::blocking::Client::new();
-            let request_maker = match &method[..] {
-                "get" => client.get(&url),
-                "post" => client.post(&url),
-                "put" => {
-                    client.put(&url)
                 },
-                "patch" => client.patch(&url),
-                "delete" => client.delete(&url),
-                "head" => client.head(&url),
                 _ => {
-                    return Err(RuntimeError::BuiltinError {
-                        builtin,
-                        message: format!("Request type not supported: '{}'", method),
-                        info
-                    })
-                }
-            };
+                    let mut headermap = reqwest::header::HeaderMap::new();
+                    for (name, value) in &headers {
+                        let header_name = match reqwest::header::HeaderName::from_bytes(name.as_bytes()) {
+                            Ok(hname) => hname,
+                            Err(_) => {
+                                return Err(RuntimeError::BuiltinError {
+                                    builtin,
+                                    message: format!("Could not convert header name: '{}'", name),
+                                    info
+                                })
+                            }
+                        };
+                        let header_value = globals.stored_values[*value].clone().to_str(globals);
+                        headermap.insert(header_name, header_value.trim_matches('\'').parse().unwrap());
+                    }
 
-            let response = match request_maker
-                .headers(headermap)
-                .body(body)
-                .send() {
-                    Ok(resp) => resp,
-                    Err(_) => {
-                        return Err(RuntimeError::BuiltinError {
-                            builtin,
-                            message: format!("Could not make request to: '{}'", url),
-                            info
-                        })
+                    let client = reqwest::blocking::Client::new();
+                    let request_maker = match &method[..] {
+                        "get" => client.get(&url),
+                        "post" => client.post(&url),
+                        "put" => {
+                            client.put(&url)
+                        },
+                        "patch" => client.patch(&url),
+                        "delete" => client.delete(&url),
+                        "head" => client.head(&url),
+                        _ => {
+                            return Err(RuntimeError::BuiltinError {
+                                builtin,
+                                message: format!("Request type not supported: '{}'", method),
+                                info
+                            })
+                        }
+                    };
+
+                    let response = match request_maker
+                        .headers(headermap)
+                        .body(body)
+                        .send() {
+                            Ok(resp) => resp,
+                            Err(_) => {
+                                return Err(RuntimeError::BuiltinError {
+                                    builtin,
+                                    message: format!("Could not make request to: '{}'", url),
+                                    info
+                                })
+                            }
+                    };
+
+                    let response = crate::http_fixtures::HttpResponse {
+                        method: method.clone(),
+                        url: url.clone(),
+                        status: response.status().as_u16(),
+                        headers: response
+                            .headers()
+                            .iter()
+                            .map(|(name, value)| (
+                                String::from(name.as_str()),
+                                String::from(value.to_str().expect("Couldn't parse return header value")),
+                            ))
+                            .collect(),
+                        text: response.text().expect("Failed to parse response text"),
+                    };
+
+                    if let Some(f) = &fixtures {
+                        if let Err(message) = f.save(&response) {
+                            return Err(RuntimeError::BuiltinError {
+                                builtin,
+                                message,
+                                info
+                            })
+                        }
                     }
+                    response
+                }
             };
 
             let mut output_map = AHashMap::default();
 
             let response_status = store_const_value(
                 Value::Number(
-                    response.status().as_u16() as f64
+                    response.status as f64
                 ),
                 globals,
                 context.start_group,
                 CodeArea::new(),
             );
 
-            let response_headermap = response.headers();
             let mut response_headers_value = AHashMap::default();
-            for (name, value) in response_headermap.iter() {
+            for (name, value) in response.headers {
                 let header_value = store_const_value(
-                    Value::Str(String::from(value.to_str().expect("Couldn't parse return header value"))),
+                    Value::Str(value),
                     globals,
                     context.start_group,
                     CodeArea::new()
                 );
-                response_headers_value.insert(LocalIntern::new(String::from(name.as_str())), header_value);
+                response_headers_value.insert(LocalIntern::new(name), header_value);
             }
 
             let response_headers = store_const_value(
@@ -910,7 +1355,7 @@ builtins! {
 
             let response_text = store_const_value(
                 Value::Str(
-                    response.text().expect("Failed to parse response text")
+                    response.text
                 ),
                 globals,
                 context.start_group,
@@ -1037,7 +1482,7 @@ $.add(obj {
                     func_id: context.func_id,
                     mode: ObjectMode::Object,
                     unique_id: globals.uid_counter,
-
+                    output: context.output_level,
                 };
                 (*globals).objects.push(obj)
             }
@@ -1058,6 +1503,44 @@ $.add(obj {
         Value::Null
     }
 
+    [OutputLevel] #[safe = true, desc = "Makes the objects and triggers added after this go in the level with the given name (in the same save file), or back in the main level when no name is given", example = "
+$.output_level('practice')
+// objects and triggers added here go in the level called 'practice'
+$.output_level()
+    "]
+    fn output_level(#["The name of the level, or nothing for the main level"]) {
+        let level = match arguments.as_slice() {
+            [] => 0,
+            [name] => match &globals.stored_values[*name] {
+                Value::Str(name) if !name.is_empty() => {
+                    match globals.output_levels.iter().position(|l| l == name) {
+                        Some(i) => i + 1,
+                        None => {
+                            globals.output_levels.push(name.clone());
+                            globals.output_levels.len()
+                        }
+                    }
+                }
+                _ => {
+                    return Err(RuntimeError::BuiltinError {
+                        builtin,
+                        message: format!("Expected the level name as a non-empty string, found {}", globals.get_type_str(*name)),
+                        info,
+                    });
+                }
+            },
+            _ => {
+                return Err(RuntimeError::BuiltinError {
+                    builtin,
+                    message: String::from("Expected 0 or 1 arguments, the name of the level"),
+                    info,
+                });
+            }
+        };
+        context.output_level = level;
+        Value::Null
+    }
+
     [Append] #[safe = true, desc = "Appends a value to the end of an array. You can also use `array.push(value)`", example = "
 let arr = []
 $.append(arr, 1)
@@ -1325,6 +1808,119 @@ $.extend_trigger_func(10g, () {
         Value::Group(context.start_group)
     }
 
+    [ItemTransfer] #[safe = true, desc = "Moves the value of an item to other items one bit at a time, leaving the source at 0. Every target is given as [item, factor], and is increased by the value times the factor. With a step larger than 1 the targets are increased by the value divided by the step instead, and the remainder is left in the source. Returns how many triggers it generated", example = "
+// adds 11i / 7 (rounded towards 0) to 10i, and leaves the remainder in 11i
+cost = $.item_transfer(11i, [[10i, 1]], 16, 7, true)
+    "]
+    fn item_transfer((source): Item, (targets): Array, (bits): Number, (step): Number, (signed): Bool) {
+        let mut factors = Vec::new();
+        for target in targets {
+            match &globals.stored_values[target] {
+                Value::Array(a) if a.len() == 2 => match (&globals.stored_values[a[0]], &globals.stored_values[a[1]]) {
+                    (Value::Item(i), Value::Number(f)) => factors.push((*i, *f)),
+                    _ => return Err(RuntimeError::TypeError {
+                        expected: "[@item, @number]".to_string(),
+                        found: globals.get_type_str(target),
+                        val_def: globals.get_area(target),
+                        info,
+                    })
+                },
+                _ => return Err(RuntimeError::TypeError {
+                    expected: "[@item, @number]".to_string(),
+                    found: globals.get_type_str(target),
+                    val_def: globals.get_area(target),
+                    info,
+                })
+            }
+        }
+        if step < 1.0 || step.fract() != 0.0 || !(1.0..=31.0).contains(&bits) {
+            return Err(RuntimeError::BuiltinError {
+                builtin,
+                message: format!("Expected a whole step of at least 1 and 1 to 31 bits, found step {} and {} bits", step, bits),
+                info,
+            });
+        }
+
+        let step = step as i64;
+        let max = (1i64 << bits as u32) - 1;
+        let mut x = 1;
+        while step * x * 2 <= max {
+            x *= 2;
+        }
+
+        let signs: &[i64] = if signed { &[1, -1] } else { &[1] };
+        let mut cost = 0;
+        // highest bit first, so every bit only has to be checked once
+        while step * x <= max && x >= 1 {
+            for sign in signs {
+                let (group, func_id) = next_fn(context.func_id, globals);
+                let (comparison, count) = if *sign > 0 { (1, step * x - 1) } else { (2, 1 - step * x) };
+
+                let obj = GdObj {
+                    params: instant_count(source, comparison, count as f64, group),
+                    mode: ObjectMode::Trigger,
+                    ..context_trigger(context, &mut globals.uid_counter)
+                }
+                .context_parameters(context);
+                globals.trigger_order += 1.0;
+                globals.func_ids[context.func_id]
+                    .obj_list
+                    .push((obj, TriggerOrder(globals.trigger_order)));
+
+                push_fn_trigger(pickup(source, (-sign * step * x) as f64), group, func_id, context.output_level, globals);
+                cost += 2;
+                for (item, factor) in &factors {
+                    if *factor != 0.0 {
+                        push_fn_trigger(pickup(*item, (sign * x) as f64 * factor), group, func_id, context.output_level, globals);
+                        cost += 1;
+                    }
+                }
+            }
+            x /= 2;
+        }
+        Value::Number(cost as f64)
+    }
+
+    [ItemSearchTree] #[safe = true, desc = "Generates a binary search over the value of an item that activates the leaf at that index. Returns the trigger function that starts the search and how many triggers the search and the leaves take", example = "
+[search, cost] = $.item_search_tree(10i, [!{ 1g.toggle_on() }, !{ 2g.toggle_on() }, !{ 3g.toggle_on() }])
+$.assert(cost == 7) // 4 for the search and one for each leaf
+search! // toggles on group 1, 2 or 3 depending on the value of 10i
+    "]
+    fn item_search_tree((item): Item, (leaves): Array) {
+        let mut groups = Vec::new();
+        for leaf in leaves {
+            match &globals.stored_values[leaf] {
+                Value::TriggerFunc(f) => groups.push(f.start_group),
+                Value::Group(g) => groups.push(*g),
+                _ => return Err(RuntimeError::TypeError {
+                    expected: "@trigger_function or @group".to_string(),
+                    found: globals.get_type_str(leaf),
+                    val_def: globals.get_area(leaf),
+                    info,
+                })
+            }
+        }
+        if groups.is_empty() {
+            return Err(RuntimeError::BuiltinError {
+                builtin,
+                message: "Expected at least one leaf".to_string(),
+                info,
+            });
+        }
+
+        // a leaf can be given more than once, but its triggers are only there once
+        let mut unique = groups.clone();
+        unique.sort();
+        unique.dedup();
+        let leaf_cost = unique.iter().map(|g| group_trigger_count(*g, globals)).sum::<usize>();
+        let (start_group, search_cost) = item_search_tree(item, &groups, 0, context.func_id, context.output_level, globals);
+        let cost = search_cost + leaf_cost;
+        Value::Array(vec![
+            store_const_value(Value::TriggerFunc(TriggerFunction { start_group }), globals, context.start_group, info.position),
+            store_const_value(Value::Number(cost as f64), globals, context.start_group, info.position),
+        ])
+    }
+
     [Random] #[safe = true, desc = "Generates random numbers, or picks a random element of an array", example = "
 $.random() // a completely random number
 $.random([1, 2, 3, 6]) // returns either 1, 2, 3, or 6
@@ -1343,8 +1939,9 @@ $.random(1..11) // returns a random integer between 1 and 10
                 });
             }
 
+            globals.random_used = true;
             if arguments.is_empty() {
-                Value::Number(rand::thread_rng().gen())
+                Value::Number(globals.rng.gen())
             } else {
                 let val = match convert_type(&globals.stored_values[arguments[0]].clone(), type_id!(array), &info, globals, context) {
                     Ok(Value::Array(v)) => v,
@@ -1358,7 +1955,7 @@ $.random(1..11) // returns a random integer between 1 and 10
                 };
 
                 if arguments.len() == 1 {
-                    let rand_elem = val.choose(&mut rand::thread_rng());
+                    let rand_elem = val.choose(&mut globals.rng);
 
                     if rand_elem.is_some() {
                         clone_and_get_value(
@@ -1387,7 +1984,7 @@ $.random(1..11) // returns a random integer between 1 and 10
                     let mut out_arr = Vec::<StoredValue>::new();
 
                     for _ in 0..times {
-                        let rand_elem = val.choose(&mut rand::thread_rng());
+                        let rand_elem = val.choose(&mut globals.rng);
 
                         if rand_elem.is_some() {
                             out_arr.push(clone_value(
@@ -1725,6 +2322,147 @@ $.random(1..11) // returns a random integer between 1 and 10
         Value::Null
     }
 
+    [Serialize] #[safe = true, desc = "Turns a value into a string in the given data format, for writing it to a file. Only dictionaries, arrays, numbers, strings, booleans and null can be serialized, unless the tagged encoding is used, which also stores IDs, trigger functions, type indicators and ranges as tagged dictionaries (like `{\"$type\": \"@group\", \"id\": 5}`)", example = "
+data = {name: 'level', ids: [1, 2, 3], hidden: false}
+$.assert($.serialize(data, 'json') == '{\"hidden\":false,\"ids\":[1,2,3],\"name\":\"level\"}')
+$.assert($.serialize({start: 5g}, 'json', true) == '{\"start\":{\"$type\":\"@group\",\"id\":5}}')
+    "]
+    fn serialize(#["The value, the data format (\"json\", \"toml\" or \"yaml\") and whether to use the tagged encoding (default: false)"]) {
+        if arguments.len() < 2 || arguments.len() > 3 {
+            return Err(RuntimeError::BuiltinError {
+                builtin,
+                message: String::from("Expected 2 or 3 arguments, the value, the data format and optionally whether to use the tagged encoding"),
+                info,
+            });
+        }
+        let format = match &globals.stored_values[arguments[1]] {
+            Value::Str(s) => s.clone(),
+            _ => {
+                return Err(RuntimeError::BuiltinError {
+                    builtin,
+                    message: "Data format needs to be a string (\"json\", \"toml\" or \"yaml\")".to_string(),
+                    info,
+                });
+            }
+        };
+        let tagged = match arguments.get(2).map(|a| &globals.stored_values[*a]) {
+            None => false,
+            Some(Value::Bool(b)) => *b,
+            Some(_) => {
+                return Err(RuntimeError::BuiltinError {
+                    builtin,
+                    message: format!("Expected a boolean for argument 3, found type {}", globals.get_type_str(arguments[2])),
+                    info,
+                });
+            }
+        };
+
+        // the error message says where in the value the problem is
+        fn to_data(val: StoredValue, path: &str, tagged: bool, globals: &Globals) -> Result<serde_json::Value, String> {
+            use serde_json::{json, Value as Json};
+            let id_json = |id: &Id, typ: &str| match id {
+                Id::Specific(n) => json!({"$type": typ, "id": n}),
+                Id::Arbitrary(n) => json!({"$type": typ, "arbitrary": n}),
+            };
+            Ok(match &globals.stored_values[val] {
+                Value::Null => Json::Null,
+                Value::Bool(b) => Json::Bool(*b),
+                Value::Number(n) => {
+                    if n.fract() == 0.0 && n.abs() < 9007199254740992.0 {
+                        json!(*n as i64)
+                    } else {
+                        match serde_json::Number::from_f64(*n) {
+                            Some(n) => Json::Number(n),
+                            None => return Err(format!("{} is {}, which can't be serialized", path, n)),
+                        }
+                    }
+                }
+                Value::Str(s) => Json::String(s.clone()),
+                Value::Array(arr) => Json::Array(
+                    arr.iter()
+                        .enumerate()
+                        .map(|(i, v)| to_data(*v, &format!("{}[{}]", path, i), tagged, globals))
+                        .collect::<Result<_, _>>()?,
+                ),
+                Value::Dict(d) => Json::Object(
+                    d.iter()
+                        .map(|(k, v)| Ok((k.to_string(), to_data(*v, &format!("{}.{}", path, k), tagged, globals)?)))
+                        .collect::<Result<_, String>>()?,
+                ),
+                Value::Group(g) if tagged => id_json(&g.id, "@group"),
+                Value::Color(c) if tagged => id_json(&c.id, "@color"),
+                Value::Block(b) if tagged => id_json(&b.id, "@block"),
+                Value::Item(i) if tagged => id_json(&i.id, "@item"),
+                Value::TriggerFunc(f) if tagged => {
+                    json!({"$type": "@trigger_function", "start_group": id_json(&f.start_group.id, "@group")})
+                }
+                Value::TypeIndicator(t) if tagged => {
+                    json!({"$type": "@type_indicator", "name": format!("@{}", find_key_for_value(&globals.type_ids, *t).unwrap())})
+                }
+                Value::Range(start, end, step) if tagged => {
+                    json!({"$type": "@range", "start": start, "end": end, "step": step})
+                }
+                Value::Group(_) | Value::Color(_) | Value::Block(_) | Value::Item(_) | Value::TriggerFunc(_) | Value::TypeIndicator(_) | Value::Range(..) => {
+                    return Err(format!(
+                        "{} is of type {}, which can only be serialized with the tagged encoding (pass true as the third argument)",
+                        path,
+                        globals.get_type_str(val)
+                    ))
+                }
+                _ => {
+                    return Err(format!(
+                        "{} is of type {}, which can't be serialized",
+                        path,
+                        globals.get_type_str(val)
+                    ))
+                }
+            })
+        }
+
+        let data = match to_data(arguments[0], "the value", tagged, globals) {
+            Ok(data) => data,
+            Err(message) => {
+                return Err(RuntimeError::BuiltinError {
+                    builtin,
+                    message,
+                    info,
+                });
+            }
+        };
+
+        let serialized = match format.as_str() {
+            "json" => serde_json::to_string(&data).map_err(|e| e.to_string()),
+            "toml" => {
+                // toml has no null, and the top level has to be a table
+                if !data.is_object() {
+                    Err("Only dictionaries can be serialized to toml".to_string())
+                } else {
+                    toml::Value::try_from(&data)
+                        .and_then(|v| toml::to_string(&v))
+                        .map_err(|e| e.to_string())
+                }
+            }
+            "yaml" => serde_yaml::to_string(&data).map_err(|e| e.to_string()),
+            _ => {
+                return Err(RuntimeError::BuiltinError {
+                    builtin,
+                    message: "Invalid data format ( use \"json\", \"toml\" or \"yaml\" )".to_string(),
+                    info,
+                })
+            }
+        };
+        match serialized {
+            Ok(s) => Value::Str(s),
+            Err(e) => {
+                return Err(RuntimeError::BuiltinError {
+                    builtin,
+                    message: format!("Problem serializing to {}: {}", format, e),
+                    info,
+                });
+            }
+        }
+    }
+
     [DeleteFile] #[safe = false, desc = "Deletes a file in the local file system", example = "$.deletefile(\"file.txt\")"] fn deletefile((path): Str) {
         match fs::remove_file(path) {
             Ok(_) => (),
@@ -2258,7 +2996,11 @@ $.assert($.call(m, [1, 2]) == 3)
 
     [IsOp] #[safe = true, desc = "Default implementation of the `is` operator", example = "$._is_([1, 2, 3], [@number])"]
     [[RAW]] fn _is_((val), (pattern)) {
-        val.matches_pat(&pattern, &info, globals, unsafe { FullContext::from_ptr(full_context) }, true)?;
+        // pattern variables are bound for the whole pattern
+        let outer = globals.pattern_bindings.replace(PatternBindings::default());
+        let result = val.matches_pat(&pattern, &info, globals, unsafe { FullContext::from_ptr(full_context) }, true);
+        globals.pattern_bindings = outer;
+        result?;
     }
 
     [NotEqOp] #[safe = true, desc = "Default implementation of the `!=` operator", example = "$._not_equal_(\"hello\", \"bye\")"]
@@ -4,8 +4,10 @@ use errors::create_error;
 use internment::LocalIntern;
 
 use shared::BreakType;
+use shared::FileRange;
 use shared::ImportType;
 use shared::SpwnSource;
+use shared::StoredValue;
 
 use crate::builtins::*;
 use crate::context::*;
@@ -14,7 +16,10 @@ use errors::compiler_info::CompilerInfo;
 use parser::ast;
 
 use crate::globals::Globals;
+use crate::http_fixtures::HttpFixtures;
 use crate::leveldata::*;
+use crate::module_cache::{self, ModuleCache};
+use crate::profiler::source_name;
 use crate::value::*;
 use crate::value_storage::*;
 use crate::STD_PATH;
@@ -34,21 +39,127 @@ use crate::compiler_types::*;
 use ariadne::Color as TColor;
 use ariadne::Fmt;
 
-pub fn compile_spwn(
+// settings for how the compiler runs, which don't change what it produces
+#[derive(Default)]
+pub struct CompileOptions {
+    pub profile: bool,
+    // no limit when this isn't set
+    pub max_contexts: Option<usize>,
+    pub trace_contexts: bool,
+    // overrides the #[seed] attribute of the script
+    pub seed: Option<u64>,
+    pub http_fixtures: Option<HttpFixtures>,
+    // answers for $.get_input, by key or prompt
+    pub inputs: AHashMap<String, String>,
+    // name and value of every -D flag, later ones override earlier ones
+    pub defines: Vec<(String, String)>,
+    // don't print the build progress (the script's own output still goes to std_out)
+    pub quiet: bool,
+}
+
+// `true`/`false` are booleans and anything that parses as a number is a number,
+// the rest (or anything in quotes) is a string
+pub fn parse_define(value: &str) -> Value {
+    match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => {
+            let quoted = value.len() >= 2
+                && ((value.starts_with('"') && value.ends_with('"'))
+                    || (value.starts_with('\'') && value.ends_with('\'')));
+            if quoted {
+                Value::Str(value[1..value.len() - 1].to_string())
+            } else if let Some(n) = value.parse::<f64>().ok().filter(|n| n.is_finite()) {
+                // "inf" and "NaN" are strings, like any other word
+                Value::Number(n)
+            } else {
+                Value::Str(value.to_string())
+            }
+        }
+    }
+}
+
+// the number in #[seed(n)]
+fn seed_attribute(args: &[ast::Argument], source: &SpwnSource) -> Result<u64, RuntimeError> {
+    if let [arg] = args {
+        if let [ast::Variable {
+            operator: None,
+            value: ast::ValueLiteral {
+                body: ast::ValueBody::Number(n),
+            },
+            path,
+            ..
+        }] = &arg.value.values[..]
+        {
+            if path.is_empty() && *n >= 0.0 && n.fract() == 0.0 {
+                return Ok(*n as u64);
+            }
+        }
+    }
+    let area = CodeArea {
+        file: LocalIntern::new(source.clone()),
+        pos: args.first().map(|a| a.pos).unwrap_or_default(),
+    };
+    Err(RuntimeError::CustomError(create_error(
+        CompilerInfo::from_area(area),
+        "The seed attribute expects one whole number",
+        &[(area, "Expected something like #[seed(42)]")],
+        None,
+    )))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn compile_spwn<'a>(
     statements: Vec<ast::Statement>,
     source: SpwnSource,
     included_paths: Vec<PathBuf>,
     notes: ParseNotes,
     permissions: BuiltinPermissions,
     initial_level: String,
-    std_out: &mut impl Write,
-) -> Result<Globals, RuntimeError> {
+    std_out: &'a mut impl Write,
+    module_cache: &'a mut ModuleCache,
+    options: CompileOptions,
+) -> Result<Globals<'a>, RuntimeError> {
     //variables that get changed throughout the compiling
 
-    let mut globals = Globals::new(source.clone(), permissions, initial_level, std_out);
+    let mut globals = Globals::new(
+        source.clone(),
+        permissions,
+        initial_level,
+        std_out,
+        module_cache,
+    );
+    globals.module_cache.reused = 0;
     globals.includes = included_paths;
+    if options.profile {
+        globals.profiler = Some(Default::default());
+    }
+    globals.max_contexts = options.max_contexts;
+    globals.trace_contexts = options.trace_contexts;
+    globals.http_fixtures = options.http_fixtures;
+    globals.inputs = options.inputs;
+    globals.defines = options
+        .defines
+        .iter()
+        .map(|(name, value)| (LocalIntern::new(name.clone()), parse_define(value)))
+        .collect();
 
-    let print_with_color = |a: &str, color| println!("{}", a.fg(color));
+    let seed = match (options.seed, notes.tag.get("seed")) {
+        (Some(seed), _) => seed,
+        (None, Some(args)) => seed_attribute(&args, &source)?,
+        #[cfg(not(target_arch = "wasm32"))]
+        (None, None) => rand::random(),
+        #[cfg(target_arch = "wasm32")]
+        (None, None) => 0,
+    };
+    globals.set_seed(seed);
+
+    let quiet = options.quiet;
+    let print_with_color = |a: &str, color| {
+        if !quiet {
+            println!("{}", a.fg(color))
+        }
+    };
 
     // if statements.is_empty() {
     //     return Err(RuntimeError::CustomError(create_error(
@@ -62,6 +173,7 @@ pub fn compile_spwn(
     //     )));
     // }
     let mut start_context = FullContext::new(&globals);
+    start_context.set_as_root();
     //store at pos 0
     // store_value(Value::Builtins, 1, &mut globals, &start_context);
     // store_value(Value::Null, 1, &mut globals, &start_context);
@@ -80,6 +192,8 @@ pub fn compile_spwn(
     #[cfg(not(target_arch = "wasm32"))]
     let start_time = Instant::now();
 
+    globals.profile_enter(|_| source_name(&source));
+
     if !notes.tag.tags.iter().any(|x| x.0 == "no_std") {
         import_module(
             &ImportType::Lib(STD_PATH.to_string()),
@@ -98,10 +212,13 @@ pub fn compile_spwn(
             )));
         }
 
-        if let Value::Dict(d) = &globals.stored_values[start_context.inner().return_value] {
+        if let Value::Dict(d) = globals.stored_values[start_context.inner().return_value].clone() {
             for (a, b, c) in d.iter().map(|(k, v)| (*k, *v, -1)) {
                 start_context.inner().new_redefinable_variable(a, b, c)
             }
+            if let Some(obj_props) = d.get(&LocalIntern::new(String::from("obj_props"))) {
+                globals.set_obj_prop_names(*obj_props);
+            }
         } else {
             return Err(RuntimeError::CustomError(create_error(
                 start_info,
@@ -132,6 +249,12 @@ pub fn compile_spwn(
         }
     }
 
+    if let Some(err) = sync_overlap_error(&globals) {
+        return Err(err);
+    }
+
+    globals.profile_exit();
+
     print_with_color("———————————————————————————\n", TColor::White);
 
     /*  Build Timing ----------------------------------------------------- **
@@ -186,10 +309,26 @@ pub fn compile_spwn(
             }
         }
 
+        let reused = match globals.module_cache.reused {
+            0 => String::new(),
+            1 => String::from(" (reused 1 unchanged module)"),
+            n => format!(" (reused {} unchanged modules)", n),
+        };
+
         print_with_color(
-            &format!("Built in{}!", time_string),
+            &format!("Built in{}!{}", time_string, reused),
             TColor::Green,
-        )
+        );
+
+        if globals.random_used {
+            print_with_color(
+                &format!(
+                    "Random seed: {} (use --seed {} to get the same results again)",
+                    globals.seed, globals.seed
+                ),
+                TColor::White,
+            );
+        }
     }
 
     //----------------------------------------------------------------------- **
@@ -199,6 +338,443 @@ pub fn compile_spwn(
 
 use crate::compiler_types::EvalExpression;
 
+// the id of a type defined with `type @name` (or `interface @name`)
+fn define_type(
+    name: &str,
+    attr: &ast::Attribute,
+    globals: &mut Globals,
+    info: &CompilerInfo,
+) -> Result<TypeId, RuntimeError> {
+    //initialize type
+    if let Some(t) = globals.type_ids.get(name) {
+        if t.1 != info.position {
+            return Err(RuntimeError::CustomError(create_error(
+                info.clone(),
+                &format!("the type '{}' is already defined", name),
+                &[
+                    (t.1, "The type was first defined here"),
+                    (info.position, "Attempted to redefine here"),
+                ],
+                None,
+            )));
+        }
+        return Ok(t.0);
+    }
+    globals.type_id_count += 1;
+    globals
+        .type_ids
+        .insert(name.to_string(), (globals.type_id_count, info.position));
+    if let Some(desc) = attr.get_desc() {
+        globals
+            .type_descriptions
+            .insert(globals.type_id_count, desc);
+    }
+    Ok(globals.type_id_count)
+}
+
+// sync a, the names are global like type names
+fn define_sync_group(
+    name: LocalIntern<String>,
+    globals: &mut Globals,
+    info: &CompilerInfo,
+) -> Result<(), RuntimeError> {
+    if let Some(g) = globals.sync_groups.iter().find(|g| g.name == name) {
+        if g.def_area != info.position {
+            return Err(RuntimeError::CustomError(create_error(
+                info.clone(),
+                &format!("the sync group '{}' is already defined", name),
+                &[
+                    (g.def_area, "The sync group was first defined here"),
+                    (info.position, "Attempted to redefine here"),
+                ],
+                None,
+            )));
+        }
+        return Ok(());
+    }
+    globals.sync_groups.push(SyncGroup {
+        name,
+        def_area: info.position,
+        parts: Vec::new(),
+        entered: AHashMap::default(),
+        uses: 0,
+    });
+    Ok(())
+}
+
+// a[part] { ... }, the IDs made in the block are only used while that part runs
+fn compile_sync_part(
+    part: &ast::SyncPart,
+    contexts: &mut FullContext,
+    globals: &mut Globals,
+    info: &CompilerInfo,
+) -> Result<(), RuntimeError> {
+    let group = match globals.sync_groups.iter().position(|g| g.name == part.group) {
+        Some(g) => g,
+        None => {
+            return Err(RuntimeError::UndefinedErr {
+                undefined: part.group.to_string(),
+                desc: "sync group".to_string(),
+                info: info.clone(),
+            })
+        }
+    };
+    part.part.eval(contexts, globals, info.clone(), true)?;
+    for full_context in contexts.iter() {
+        let (context, val) = full_context.inner_value();
+        let fn_context = context.start_group;
+        let value = globals.stored_values[val].clone();
+        let name = value.clone().to_str(globals);
+
+        let sync_group = &mut globals.sync_groups[group];
+        let id = match sync_group.parts.iter().position(|(p, _)| *p == value) {
+            Some(id) => id,
+            None => {
+                sync_group.parts.push((value, name));
+                sync_group.parts.len() - 1
+            }
+        };
+        // everything a trigger function runs happens at the same time
+        match sync_group.entered.get(&fn_context) {
+            Some((other, area)) if *other != id => {
+                return Err(RuntimeError::CustomError(create_error(
+                    info.clone(),
+                    &format!(
+                        "parts {} and {} of the sync group '{}' run at the same time",
+                        sync_group.parts[*other].1, sync_group.parts[id].1, sync_group.name
+                    ),
+                    &[
+                        (*area, &format!("Part {} runs here", sync_group.parts[*other].1)),
+                        (
+                            info.position,
+                            &format!(
+                                "Part {} runs here in the same trigger function",
+                                sync_group.parts[id].1
+                            ),
+                        ),
+                    ],
+                    None,
+                )));
+            }
+            Some(_) => (),
+            None => {
+                sync_group.entered.insert(fn_context, (id, info.position));
+            }
+        }
+        sync_group.uses += 1;
+
+        let before = module_cache::counters(globals);
+        compile_scope(&part.body, full_context, globals, info.clone())?;
+        let after = module_cache::counters(globals);
+        for class in 0..4 {
+            for arbitrary in before[class] + 1..=after[class] {
+                // the IDs made in parts inside this one belong to those
+                globals.sync_ids[class].entry(arbitrary).or_insert((group, id));
+            }
+        }
+    }
+    Ok(())
+}
+
+// the contexts that start a trigger function, with the call each of them makes to get there
+// (none for the function itself)
+fn started_from(start: Group, calls: &[FnCall]) -> AHashMap<Group, Option<&FnCall>> {
+    let mut found = AHashMap::default();
+    found.insert(start, None);
+    let mut stack = vec![start];
+    while let Some(group) = stack.pop() {
+        for call in calls.iter().filter(|c| c.target == group) {
+            if !found.contains_key(&call.from) {
+                found.insert(call.from, Some(call));
+                stack.push(call.from);
+            }
+        }
+    }
+    found
+}
+
+// two parts of a sync group in trigger functions that are started from the same context (even
+// through other calls) run at the same time. this is checked after the build, since a function
+// can be called after the parts in it are compiled
+fn sync_overlap_error(globals: &Globals) -> Option<RuntimeError> {
+    for sync_group in &globals.sync_groups {
+        let mut entered = sync_group
+            .entered
+            .iter()
+            .map(|(group, (part, area))| (*group, *part, *area))
+            .collect::<Vec<_>>();
+        entered.sort_by_key(|(_, part, area)| (*part, area.pos));
+        let starts = entered
+            .iter()
+            .map(|(group, _, _)| started_from(*group, &globals.fn_calls))
+            .collect::<Vec<_>>();
+
+        for (i, (_, part1, area1)) in entered.iter().enumerate() {
+            for (j, (_, part2, area2)) in entered.iter().enumerate().skip(i + 1) {
+                if part1 == part2 {
+                    continue;
+                }
+                let order = |call: &Option<&FnCall>| call.map(|c| c.order).unwrap_or(-1.0);
+                // the context that starts both first
+                let common = starts[i]
+                    .iter()
+                    .filter_map(|(group, call1)| Some((*call1, *starts[j].get(group)?)))
+                    .min_by(|a, b| {
+                        (order(&a.0), order(&a.1))
+                            .partial_cmp(&(order(&b.0), order(&b.1)))
+                            .unwrap()
+                    });
+                let (call1, call2) = match common {
+                    Some(c) => c,
+                    None => continue,
+                };
+
+                let name1 = &sync_group.parts[*part1].1;
+                let name2 = &sync_group.parts[*part2].1;
+                let mut labels = vec![
+                    (*area1, format!("Part {} runs here", name1)),
+                    (*area2, format!("Part {} runs here", name2)),
+                ];
+                for (call, name) in [(call1, name1), (call2, name2)] {
+                    if let Some(call) = call {
+                        labels.push((
+                            call.area,
+                            format!("This starts part {} at the same time", name),
+                        ));
+                    }
+                }
+                return Some(RuntimeError::CustomError(create_error(
+                    CompilerInfo::from_area(*area2),
+                    &format!(
+                        "parts {} and {} of the sync group '{}' run at the same time",
+                        name1, name2, sync_group.name
+                    ),
+                    &labels
+                        .iter()
+                        .map(|(area, text)| (*area, text.as_str()))
+                        .collect::<Vec<_>>(),
+                    None,
+                )));
+            }
+        }
+    }
+    None
+}
+
+// impl @type: @interface { ... } has to leave the type with every member of the interface
+// checked before the members are added, so a rejected impl leaves the type as it was
+fn check_interface(
+    typ: TypeId,
+    interface: &ast::Variable,
+    new_members: &[LocalIntern<String>],
+    contexts: &mut FullContext,
+    globals: &mut Globals,
+    info: &CompilerInfo,
+) -> Result<(), RuntimeError> {
+    interface.to_value(contexts, globals, info.clone(), true)?;
+    if let FullContext::Split(_, _) = contexts {
+        return Err(RuntimeError::CustomError(create_error(
+            info.clone(),
+            "impl statements with context-splitting values are not allowed",
+            &[],
+            None,
+        )));
+    }
+    let val = contexts.inner().return_value;
+    let interface = match globals.stored_values[val] {
+        Value::TypeIndicator(i) if globals.interfaces.contains_key(&i) => i,
+        Value::TypeIndicator(i) => {
+            return Err(RuntimeError::CustomError(create_error(
+                info.clone(),
+                &format!(
+                    "@{} is not an interface",
+                    find_key_for_value(&globals.type_ids, i).unwrap()
+                ),
+                &[(globals.get_area(val), "Used as an interface here")],
+                None,
+            )))
+        }
+        ref a => {
+            return Err(RuntimeError::TypeError {
+                expected: "interface".to_string(),
+                found: a.get_type_str(globals),
+                val_def: globals.get_area(val),
+                info: info.clone(),
+            })
+        }
+    };
+
+    let missing = globals
+        .missing_members(typ, interface)
+        .into_iter()
+        .filter(|m| !new_members.contains(m))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        let missing = missing.iter().map(|m| m.as_str()).collect::<Vec<_>>();
+        return Err(RuntimeError::CustomError(create_error(
+            info.clone(),
+            &format!(
+                "@{} doesn't implement every member of @{}",
+                find_key_for_value(&globals.type_ids, typ).unwrap(),
+                find_key_for_value(&globals.type_ids, interface).unwrap()
+            ),
+            &[(info.position, &format!("Missing: {}", missing.join(", ")))],
+            None,
+        )));
+    }
+    Ok(())
+}
+
+// enum values are dictionaries with the type, the name of the variant and the fields,
+// and every variant gets a constructor in the implementation of the type
+fn define_enum(
+    typ: TypeId,
+    name: &str,
+    variants: &[ast::EnumVariant],
+    globals: &mut Globals,
+    info: &CompilerInfo,
+) -> Result<(), RuntimeError> {
+    for variant in variants {
+        if let Some(field) = variant
+            .fields
+            .iter()
+            .find(|f| **f == globals.TYPE_MEMBER_NAME || **f == globals.VARIANT_MEMBER_NAME)
+        {
+            return Err(RuntimeError::CustomError(create_error(
+                info.clone(),
+                &format!("'{}' can't be used as the name of a field", field),
+                &[(
+                    CodeArea {
+                        pos: variant.pos,
+                        ..info.position
+                    },
+                    "Field defined in this variant",
+                )],
+                Some("the 'type' and 'variant' keys are used by the enum value itself"),
+            )));
+        }
+    }
+    globals.enum_variants.insert(typ, variants.to_vec());
+
+    let fn_context = Group::new(0);
+    for variant in variants {
+        let area = CodeArea {
+            pos: variant.pos,
+            ..info.position
+        };
+        let constructor = if variant.fields.is_empty() {
+            // variants without fields don't have to be called
+            let mut dict = AHashMap::default();
+            dict.insert(
+                globals.TYPE_MEMBER_NAME,
+                store_const_value(Value::TypeIndicator(typ), globals, fn_context, area),
+            );
+            dict.insert(
+                globals.VARIANT_MEMBER_NAME,
+                store_const_value(Value::Str(variant.name.to_string()), globals, fn_context, area),
+            );
+            store_const_value(Value::Dict(dict), globals, fn_context, area)
+        } else {
+            // a macro that returns @type::{variant: "name", field: field, ...}
+            let expr = |body: ast::ValueBody| body.to_variable(variant.pos).to_expression();
+            let mut members = vec![ast::DictDef::Def((
+                globals.VARIANT_MEMBER_NAME,
+                expr(ast::ValueBody::Str(ast::StrInner {
+                    inner: variant.name.to_string(),
+                    flags: None,
+                })),
+            ))];
+            members.extend(
+                variant
+                    .fields
+                    .iter()
+                    .map(|f| ast::DictDef::Def((*f, expr(ast::ValueBody::Symbol(*f))))),
+            );
+            let mut value = ast::ValueBody::TypeIndicator(name.to_string()).to_variable(variant.pos);
+            value.path.push(ast::Path::Constructor(members));
+
+            let body = vec![ast::Statement {
+                body: ast::StatementBody::Return(Some(value.to_expression())),
+                arrow: false,
+                pos: variant.pos,
+            }];
+            let args = variant
+                .fields
+                .iter()
+                .map(|f| MacroArgDef {
+                    name: *f,
+                    default: None,
+                    attribute: ast::Attribute::new(),
+                    pattern: None,
+                    position: variant.pos,
+                    arg_type: ast::ArgType::Const,
+                })
+                .collect();
+            store_const_value(
+                Value::Macro(Macro::FuncLike(MacroFuncData {
+                    args,
+                    def_variables: AHashMap::default(),
+                    def_file: info.position.file,
+                    def_module: info.current_module.clone(),
+                    body,
+                    tag: ast::Attribute::new(),
+                    arg_pos: variant.pos,
+                    ret_pattern: None,
+                })),
+                globals,
+                fn_context,
+                area,
+            )
+        };
+        globals
+            .implementations
+            .entry(typ)
+            .or_default()
+            .insert(variant.name, (constructor, true));
+    }
+    Ok(())
+}
+
+// what a catch gets: a dictionary with the kind of error, its message and where it happened
+fn error_value(
+    err: RuntimeError,
+    globals: &mut Globals,
+    fn_context: Group,
+    area: CodeArea,
+) -> StoredValue {
+    let kind = err.kind();
+    // the report only has a general message for these
+    let message = match &err {
+        RuntimeError::BuiltinError { message, .. } => Some(message.clone()),
+        RuntimeError::ThrowError { messages, .. } => Some(
+            messages
+                .iter()
+                .map(|(_, m)| m.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        _ => None,
+    };
+    let report = errors::ErrorReport::from(err);
+    let location = globals.source_lines.location_name(&report.info.position);
+
+    let fields = [
+        ("kind", kind.to_string()),
+        ("message", message.unwrap_or(report.message)),
+        ("area", location),
+    ];
+    let dict = fields
+        .into_iter()
+        .map(|(key, val)| {
+            (
+                LocalIntern::new(key.to_string()),
+                store_const_value(Value::Str(val), globals, fn_context, area),
+            )
+        })
+        .collect();
+    store_const_value(Value::Dict(dict), globals, fn_context, area)
+}
+
 pub fn compile_scope(
     statements: &[ast::Statement],
     contexts: &mut FullContext,
@@ -226,6 +802,9 @@ pub fn compile_scope(
         // );
         info.position.pos = statement.pos;
 
+        // (number of contexts, splits recorded so far) to find the splits made by this statement
+        let splits_before = (contexts.with_breaks().count(), globals.context_splits);
+
         // println!(
         //     "{}:0:{}",
         //     info.position.file.to_string_lossy(),
@@ -327,35 +906,30 @@ pub fn compile_scope(
                 }
             }
 
-            TypeDef { name, attr } => {
-                //initialize type
-                let already = globals.type_ids.get(name);
-                if let Some(t) = already {
-                    if t.1 != info.position {
-                        return Err(RuntimeError::CustomError(create_error(
-                            info.clone(),
-                            &format!("the type '{}' is already defined", name),
-                            &[
-                                (t.1, "The type was first defined here"),
-                                (info.position, "Attempted to redefine here"),
-                            ],
-                            None,
-                        )));
-                    }
-                } else {
-                    (*globals).type_id_count += 1;
-                    (*globals)
-                        .type_ids
-                        .insert(name.clone(), (globals.type_id_count, info.position));
-                    if let Some(desc) = attr.get_desc() {
-                        (*globals)
-                            .type_descriptions
-                            .insert(globals.type_id_count, desc);
-                    }
+            TypeDef {
+                name,
+                attr,
+                variants,
+            } => {
+                let typ = define_type(name, attr, globals, &info)?;
+                if !variants.is_empty() {
+                    define_enum(typ, name, variants, globals, &info)?;
                 }
-                //Value::TypeIndicator(globals.type_id_count)
             }
 
+            Interface {
+                name,
+                attr,
+                members,
+            } => {
+                let typ = define_type(name, attr, globals, &info)?;
+                globals.interfaces.insert(typ, members.clone());
+            }
+
+            SyncGroup(name) => define_sync_group(*name, globals, &info)?,
+
+            SyncPart(part) => compile_sync_part(part, contexts, globals, &info)?,
+
             If(if_stmt) => {
                 if_stmt
                     .condition
@@ -457,6 +1031,14 @@ pub fn compile_scope(
 
                         // make this not ugly, future me
 
+                        if let Some(interface) = &imp.interface {
+                            let new_members = match &globals.stored_values[val] {
+                                Value::Dict(d) => d.keys().copied().collect::<Vec<_>>(),
+                                _ => unreachable!(),
+                            };
+                            check_interface(s, interface, &new_members, contexts, globals, &info)?;
+                        }
+
                         if let Value::Dict(d) = &globals.stored_values[val] {
                             match globals.implementations.get_mut(&s) {
                                 Some(implementation) => {
@@ -499,24 +1081,28 @@ pub fn compile_scope(
                 //let mut obj_list = Vec::<GDObj>::new();
                 for full_context in contexts.iter() {
                     let (context, func) = full_context.inner_value();
+                    let target = match &globals.stored_values[func] {
+                        Value::TriggerFunc(g) => g.start_group,
+                        Value::Group(g) => *g,
+                        a => {
+                            return Err(RuntimeError::TypeError {
+                                expected: "trigger function or group".to_string(),
+                                found: a.get_type_str(globals),
+                                val_def: globals.get_area(func),
+                                info,
+                            })
+                        }
+                    };
                     let mut params = AHashMap::default();
-                    params.insert(
-                        51,
-                        match &globals.stored_values[func] {
-                            Value::TriggerFunc(g) => ObjParam::Group(g.start_group),
-                            Value::Group(g) => ObjParam::Group(*g),
-                            a => {
-                                return Err(RuntimeError::TypeError {
-                                    expected: "trigger function or group".to_string(),
-                                    found: a.get_type_str(globals),
-                                    val_def: globals.get_area(func),
-                                    info,
-                                })
-                            }
-                        },
-                    );
+                    params.insert(51, ObjParam::Group(target));
                     params.insert(1, ObjParam::Number(1268.0));
                     (*globals).trigger_order += 1.0;
+                    globals.fn_calls.push(FnCall {
+                        target,
+                        from: context.start_group,
+                        order: globals.trigger_order,
+                        area: info.position,
+                    });
 
                     (*globals).func_ids[context.func_id].obj_list.push((
                         GdObj {
@@ -942,20 +1528,57 @@ pub fn compile_scope(
 
                 e.message.eval(contexts, globals, info.clone(), true)?;
                 for c in contexts.iter() {
-                    let err = globals.stored_values[c.inner().return_value]
-                        .clone()
-                        .to_str(globals);
+                    // strings are thrown as they are, so they can be caught without quotes
+                    let err = match &globals.stored_values[c.inner().return_value] {
+                        Value::Str(s) => s.clone(),
+                        val => val.clone().to_str(globals),
+                    };
                     errors.push((info.position, err))
                 }
 
-                let mut new_errors = Vec::new();
-                for (area, msg) in errors.iter() {
-                    new_errors.push((*area, msg.as_str()))
-                }
-
-                let err = create_error(info, "Runtime Error", &new_errors, None);
+                return Err(RuntimeError::ThrowError {
+                    messages: errors,
+                    info,
+                });
+            }
 
-                return Err(RuntimeError::CustomError(err));
+            TryCatch(t) => {
+                for full_context in contexts.iter() {
+                    // the try body runs on a copy, so if it fails its scope and splits are thrown away,
+                    // and what it added to the level is taken out again (values it changed stay changed)
+                    let mut attempt = full_context.clone();
+                    let level = globals.level_state();
+                    let err = match compile_scope(&t.try_body, &mut attempt, globals, info.clone()) {
+                        Ok(()) => {
+                            *full_context = attempt;
+                            continue;
+                        }
+                        // the build couldn't go on after this anyway
+                        Err(e @ RuntimeError::ContextLimitError { .. }) => return Err(e),
+                        Err(e) => e,
+                    };
+                    globals.restore_level_state(level);
+
+                    if let Some(symbol) = &t.catch_symbol {
+                        let fn_context = full_context.inner().start_group;
+                        let err = error_value(err, globals, fn_context, info.position);
+                        do_assignment(
+                            symbol,
+                            &Some(
+                                ast::ValueBody::Resolved(err)
+                                    .to_variable(statement.pos)
+                                    .to_expression(),
+                            ),
+                            full_context,
+                            globals,
+                            &info,
+                            false,
+                            -1, // so that it gets removed at the end of the catch body
+                            None,
+                        )?;
+                    }
+                    compile_scope(&t.catch_body, full_context, globals, info.clone())?;
+                }
             }
         }
 
@@ -984,8 +1607,31 @@ pub fn compile_scope(
             *contexts = FullContext::stack(&mut list.into_iter()).unwrap();
         }
 
+        // splits that happened inside a macro or import called here are recorded there
+        let new_contexts = contexts.with_breaks().count().saturating_sub(splits_before.0);
+        let inner = globals.context_splits - splits_before.1;
+        let splits = new_contexts.saturating_sub(inner);
+        if splits > 0 {
+            globals.context_splits += splits;
+            globals.trace_contexts(
+                &format!(
+                    "statement made {} new context{}",
+                    splits,
+                    if splits == 1 { "" } else { "s" }
+                ),
+                &info.position,
+            );
+            for c in contexts.with_breaks() {
+                c.inner().split_stack.push(info.position);
+            }
+            if let Some(profiler) = &mut globals.profiler {
+                profiler.record_splits(splits);
+            }
+        }
+
         //try to merge contexts
-        merge_all_contexts(contexts, globals, false);
+        merge_all_contexts(contexts, globals, false, info.position);
+        check_context_limit(contexts, globals, &info)?;
 
         if contexts.iter().next().is_none() {
             break;
@@ -1071,6 +1717,7 @@ pub fn do_assignment(
     if let ast::ValueBody::Array(arr) = &dest.value.body {
         destructure_sanitize(src)?;
         array_destructure_define(
+            dest,
             arr,
             src.as_ref().unwrap(),
             contexts,
@@ -1083,7 +1730,7 @@ pub fn do_assignment(
         Ok(())
     } else if let ast::ValueBody::Dictionary(kvs) = &dest.value.body {
         destructure_sanitize(src)?;
-        dict_destructure_define(kvs, info, src, contexts, globals, mutable, concat)?;
+        dict_destructure_define(dest, kvs, info, src, contexts, globals, mutable, scope, concat)?;
         Ok(())
     } else {
         // no destructure here
@@ -1323,13 +1970,36 @@ pub fn do_assignment(
     }
 }
 
+// a value that doesn't have the shape of the destructure it's assigned to
+fn destructure_mismatch(
+    dest: &ast::Variable,
+    val: StoredValue,
+    globals: &mut Globals,
+    info: &CompilerInfo,
+) -> RuntimeError {
+    use parser::fmt::SpwnFmt;
+    RuntimeError::PatternMismatchError {
+        pattern: SpwnFmt::fmt(dest, 0),
+        val: globals.stored_values[val].clone().to_str(globals),
+        pat_def: CodeArea {
+            pos: dest.pos,
+            ..info.position
+        },
+        val_def: globals.get_area(val),
+        info: info.clone(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn dict_destructure_define(
+    dest: &ast::Variable,
     kvs: &[ast::DictDef],
     info: &CompilerInfo,
     src: &Option<ast::Expression>,
     contexts: &mut FullContext,
     globals: &mut Globals,
     mutable: bool,
+    scope: i16,
     concat: Option<bool>,
 ) -> Result<(), RuntimeError> {
     let ranges: Vec<&ast::Expression> = kvs
@@ -1363,14 +2033,7 @@ fn dict_destructure_define(
 
         let mut evaled_src = match globals.stored_values[evaled_store].clone() {
             Value::Dict(d) => d,
-            b => {
-                return Err(RuntimeError::TypeError {
-                    expected: "dictionary".to_string(),
-                    found: b.get_type_str(globals),
-                    val_def: globals.get_area(evaled_store),
-                    info: info.clone(),
-                })
-            }
+            _ => return Err(destructure_mismatch(dest, evaled_store, globals, info)),
         };
 
         let mut collected = Vec::<&LocalIntern<String>>::new();
@@ -1379,12 +2042,7 @@ fn dict_destructure_define(
                 ast::DictDef::Extract(_) => (),
                 ast::DictDef::Def((key, value)) => {
                     if !evaled_src.contains_key(key) {
-                        return Err(RuntimeError::CustomError(create_error(
-                            info.clone(),
-                            &format!("Key '{}' not found", key),
-                            &[],
-                            None,
-                        )));
+                        return Err(destructure_mismatch(dest, evaled_store, globals, info));
                     }
 
                     collected.push(key);
@@ -1395,7 +2053,7 @@ fn dict_destructure_define(
                         globals,
                         info,
                         mutable,
-                        0,
+                        scope,
                         concat,
                     )?;
                 }
@@ -1428,7 +2086,7 @@ fn dict_destructure_define(
                 globals,
                 info,
                 mutable,
-                0,
+                scope,
                 concat,
             )?;
         }
@@ -1438,6 +2096,7 @@ fn dict_destructure_define(
 
 #[allow(clippy::too_many_arguments)]
 fn array_destructure_define(
+    dest: &ast::Variable,
     arr: &[ast::ArrayDef],
     value: &ast::Expression,
     contexts: &mut FullContext,
@@ -1449,7 +2108,8 @@ fn array_destructure_define(
 ) -> Result<(), RuntimeError> {
     value.eval(contexts, globals, info.clone(), true)?;
     for ctx in contexts.iter() {
-        match globals.stored_values[ctx.inner().return_value].clone() {
+        let val = ctx.inner().return_value;
+        match globals.stored_values[val].clone() {
             Value::Array(val_a) => {
                 let ranges = arr
                     .iter()
@@ -1479,75 +2139,38 @@ fn array_destructure_define(
                     )));
                 }
 
-                if (arr.len() < val_a.len() && ranges.is_empty()) || arr.len() > val_a.len() {
-                    return Err(RuntimeError::CustomError(create_error(
-                        info.clone(),
-                        &format!(
-                            "Expected {} items to destructure, found {}",
-                            arr.len(),
-                            val_a.len()
-                        ),
-                        &[],
-                        None,
-                    )));
-                } else {
-                    let mut idx: usize = 0;
-                    let mut var_idx: usize = 0;
-                    loop {
-                        let mut idx_step = 1;
-                        for expr_ctx in ctx.iter() {
-                            if arr[var_idx].value.values.is_empty() {
-                                return Err(RuntimeError::CustomError(create_error(
-                                    info.clone(),
-                                    "Cannot destructure value into empty expression",
-                                    &[],
-                                    None,
-                                )));
-                            }
-
-                            let the_expr = &arr[var_idx].value;
-                            match arr[var_idx].operator {
-                                Some(ast::ArrayPrefix::Collect | ast::ArrayPrefix::Spread) => {
-                                    idx_step = 1 + val_a.len() - arr.len();
+                // everything that isn't spread needs exactly one item
+                let fixed = arr.len() - ranges.len();
+                if val_a.len() < fixed || (ranges.is_empty() && val_a.len() != fixed) {
+                    return Err(destructure_mismatch(dest, val, globals, info));
+                }
 
-                                    /*let astvec = ast::ValueBody::Array(
-                                        (idx..(idx + idx_step))
-                                            .map(|tmp_idx| ast::ArrayDef {
-                                                value: ast::ValueBody::Resolved(val_a[tmp_idx])
-                                                    .to_variable(the_expr.values[0].pos)
-                                                    .to_expression(),
-                                                operator: None,
-                                            })
-                                            .collect(),
-                                    )
-                                    .to_variable(the_expr.values[0].pos)
-                                    .to_expression();*/
+                let mut idx: usize = 0;
+                for def in arr {
+                    let mut idx_step = 1;
+                    for expr_ctx in ctx.iter() {
+                        if def.value.values.is_empty() {
+                            return Err(RuntimeError::CustomError(create_error(
+                                info.clone(),
+                                "Cannot destructure value into empty expression",
+                                &[],
+                                None,
+                            )));
+                        }
 
-                                    let mut overwrite = true;
+                        let the_expr = &def.value;
+                        match def.operator {
+                            Some(ast::ArrayPrefix::Collect | ast::ArrayPrefix::Spread) => {
+                                idx_step = val_a.len() - fixed;
 
-                                    for stored_value in val_a.iter().skip(idx).take(idx_step) {
-                                        do_assignment(
-                                            the_expr,
-                                            &Some(
-                                                ast::ValueBody::Resolved(*stored_value)
-                                                    .to_variable(the_expr.values[0].pos)
-                                                    .to_expression(),
-                                            ),
-                                            expr_ctx,
-                                            globals,
-                                            info,
-                                            mutable,
-                                            scope,
-                                            Some(overwrite),
-                                        )?;
-                                        overwrite = false;
-                                    }
-                                }
-                                _ => {
+                                if idx_step == 0 {
+                                    // nothing left for the spread
                                     do_assignment(
                                         the_expr,
                                         &Some(
-                                            stored_to_variable(val_a[idx], globals).to_expression(),
+                                            ast::ValueBody::Array(Vec::new())
+                                                .to_variable(the_expr.values[0].pos)
+                                                .to_expression(),
                                         ),
                                         expr_ctx,
                                         globals,
@@ -1557,25 +2180,46 @@ fn array_destructure_define(
                                         concat,
                                     )?;
                                 }
-                            }
-                        }
 
-                        idx += idx_step;
-                        var_idx += 1;
-                        if idx >= val_a.len() {
-                            break;
+                                let mut overwrite = true;
+
+                                for stored_value in val_a.iter().skip(idx).take(idx_step) {
+                                    do_assignment(
+                                        the_expr,
+                                        &Some(
+                                            ast::ValueBody::Resolved(*stored_value)
+                                                .to_variable(the_expr.values[0].pos)
+                                                .to_expression(),
+                                        ),
+                                        expr_ctx,
+                                        globals,
+                                        info,
+                                        mutable,
+                                        scope,
+                                        Some(overwrite),
+                                    )?;
+                                    overwrite = false;
+                                }
+                            }
+                            _ => {
+                                do_assignment(
+                                    the_expr,
+                                    &Some(stored_to_variable(val_a[idx], globals).to_expression()),
+                                    expr_ctx,
+                                    globals,
+                                    info,
+                                    mutable,
+                                    scope,
+                                    concat,
+                                )?;
+                            }
                         }
                     }
+
+                    idx += idx_step;
                 }
             }
-            b => {
-                return Err(RuntimeError::TypeError {
-                    expected: "array".to_string(),
-                    found: b.get_type_str(globals),
-                    val_def: globals.get_area(ctx.inner().return_value),
-                    info: info.clone(),
-                })
-            }
+            _ => return Err(destructure_mismatch(dest, val, globals, info)),
         }
     }
     Ok(())
@@ -1585,6 +2229,7 @@ pub fn merge_all_contexts(
     contexts: &mut FullContext,
     globals: &mut Globals,
     check_return_vals: bool,
+    position: CodeArea,
 ) {
     if let FullContext::Split(_, _) = contexts {
         let mut broken = Vec::new();
@@ -1598,12 +2243,24 @@ pub fn merge_all_contexts(
         }
 
         if not_broken.len() > 1 {
+            let before = not_broken.len();
             loop {
                 if !merge_contexts(&mut not_broken, globals, check_return_vals) {
                     break;
                 }
             }
 
+            if not_broken.len() < before {
+                globals.trace_contexts(
+                    &format!("merged {} contexts into {}", before, not_broken.len()),
+                    &position,
+                );
+            }
+            if not_broken.len() == 1 && broken.is_empty() {
+                // back to one context, so the old splits don't matter anymore
+                not_broken[0].split_stack.clear();
+            }
+
             broken.extend(not_broken);
 
             *contexts =
@@ -1612,6 +2269,51 @@ pub fn merge_all_contexts(
     }
 }
 
+// stops the build when the contexts have split too much, showing how it happened
+pub fn check_context_limit(
+    contexts: &mut FullContext,
+    globals: &Globals,
+    info: &CompilerInfo,
+) -> Result<(), RuntimeError> {
+    // counting the contexts walks all of them, so it's only done when there is a limit
+    match globals.max_contexts {
+        Some(max) if contexts.with_breaks().count() > max => (),
+        _ => return Ok(()),
+    }
+    Err(context_limit_error(
+        &mut contexts.with_breaks(),
+        globals,
+        info,
+    ))
+}
+
+pub fn context_limit_error<'a>(
+    contexts: &mut impl Iterator<Item = &'a mut FullContext>,
+    globals: &Globals,
+    info: &CompilerInfo,
+) -> RuntimeError {
+    let mut count = 0;
+    let mut most_split: Option<&Context> = None;
+    for c in contexts {
+        for c in c.with_breaks() {
+            count += 1;
+            let c = c.inner();
+            // the context that was split the most shows best where the splits come from
+            if !matches!(most_split, Some(m) if m.split_stack.len() >= c.split_stack.len()) {
+                most_split = Some(c);
+            }
+        }
+    }
+    let context = most_split.unwrap();
+    RuntimeError::ContextLimitError {
+        limit: globals.max_contexts.unwrap(),
+        count,
+        info: info.clone(),
+        splits: context.split_stack.clone(),
+        context_changes: context.fn_context_change_stack.clone(),
+    }
+}
+
 fn merge_impl(target: &mut Implementations, source: &Implementations) {
     for (key, imp) in source.iter() {
         match target.get_mut(key) {
@@ -1706,9 +2408,48 @@ pub fn import_module(
     globals: &mut Globals,
     info: CompilerInfo,
     forced: bool,
+) -> Result<(), RuntimeError> {
+    globals.profile_enter(|_| match path {
+        ImportType::Script(p) => format!("import {}", p.display()),
+        ImportType::Lib(name) => format!("import {}", name),
+    });
+    let result = import_module_body(path, contexts, globals, info, forced);
+    globals.profile_exit();
+    result
+}
+
+// a module with exports returns a dictionary of them, like it would with a return statement
+// at the end of the file
+fn export_statement(exports: &[(LocalIntern<String>, FileRange)]) -> ast::Statement {
+    let pos = exports[0].1;
+    let dict = exports
+        .iter()
+        .map(|(name, pos)| {
+            ast::DictDef::Def((
+                *name,
+                ast::ValueBody::Symbol(*name).to_variable(*pos).to_expression(),
+            ))
+        })
+        .collect();
+    ast::Statement {
+        body: ast::StatementBody::Return(Some(
+            ast::ValueBody::Dictionary(dict).to_variable(pos).to_expression(),
+        )),
+        arrow: false,
+        pos,
+    }
+}
+
+fn import_module_body(
+    path: &ImportType,
+    contexts: &mut FullContext,
+    globals: &mut Globals,
+    info: CompilerInfo,
+    forced: bool,
 ) -> Result<(), RuntimeError> {
     if !forced {
         if let Some(ret) = globals.prev_imports.get(path).cloned() {
+            module_cache::add_import_deps(globals, path);
             merge_impl(&mut globals.implementations, &ret.1);
             for c in contexts.iter() {
                 c.inner().return_value = ret.0;
@@ -1804,13 +2545,39 @@ pub fn import_module(
         }
     };
 
+    // the module adds its objects to the level it was imported in
+    let output_level = contexts
+        .iter()
+        .next()
+        .map(|c| c.inner().output_level)
+        .unwrap_or_default();
+    let fingerprint =
+        module_cache::fingerprint(globals, path, &module_path, &unparsed, output_level);
+    if let Some(output) = module_cache::reuse(globals, path, &module_path, fingerprint) {
+        if let Some(v) = output {
+            for full_context in contexts.iter() {
+                let fn_context = full_context.inner().start_group;
+                (*full_context).inner().return_value = match v {
+                    Some(v) => clone_value(v, globals, fn_context, true, info.position),
+                    None => globals.NULL_STORAGE,
+                };
+            }
+        }
+        (*globals).built_in_path = stored_built_in_path;
+        return Ok(());
+    }
+
     let (parsed, notes) =
         match parser::parser::parse_spwn(unparsed, module_path.clone(), BUILTIN_NAMES) {
             Ok(p) => p,
             Err(err) => return Err(RuntimeError::PackageSyntaxError { err, info }),
         };
 
+    let import_state = module_cache::ImportState::new(globals, path, &module_path, fingerprint);
+
     let mut start_context = FullContext::new(globals);
+    start_context.set_as_root();
+    start_context.inner().output_level = output_level;
 
     globals.push_new_preserved();
     for c in contexts.with_breaks() {
@@ -1880,7 +2647,30 @@ pub fn import_module(
     new_info.position.pos = (0, 0);
 
     if let ImportType::Lib(l) = path {
-        new_info.current_module = l.clone();
+        new_info.enter_module(l.clone());
+    }
+
+    let exports = parsed
+        .iter()
+        .filter_map(|s| match &s.body {
+            ast::StatementBody::Definition(ast::Definition {
+                symbol,
+                exported: true,
+                ..
+            }) => match &symbol.value.body {
+                ast::ValueBody::Symbol(name) => Some((*name, s.pos)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let export_pos = exports.first().map(|(_, pos)| *pos);
+    let mut parsed = parsed;
+    if !exports.is_empty() {
+        parsed.push(export_statement(&exports));
+        globals
+            .module_exports
+            .insert(path.clone(), exports.iter().map(|(name, _)| *name).collect());
     }
 
     match compile_scope(&parsed, &mut start_context, globals, new_info) {
@@ -1895,10 +2685,36 @@ pub fn import_module(
 
     globals.pop_preserved();
 
+    if let Some(export) = export_pos {
+        // the only return has to be the one that was added for the exports
+        for fc in start_context.with_breaks() {
+            if let Some((BreakType::Macro(_, _), area)) = fc.inner().broken {
+                if area.pos != export {
+                    return Err(RuntimeError::CustomError(create_error(
+                        info,
+                        "A module with exports can't also return a value",
+                        &[
+                            (
+                                CodeArea {
+                                    pos: export,
+                                    ..area
+                                },
+                                "Exported here",
+                            ),
+                            (area, "Returned here"),
+                        ],
+                        None,
+                    )));
+                }
+            }
+        }
+    }
+
     let save_value = notes.tag.tags.iter().any(|x| x.0 == "cache_output");
     let mut out_values = 0;
     let mut output_saved = None;
     let mut impl_saved = None;
+    let mut module_impls = None;
 
     for fc in start_context.with_breaks() {
         let c = fc.inner();
@@ -1960,6 +2776,7 @@ pub fn import_module(
         if save_value {
             impl_saved = Some(globals.implementations.clone());
         }
+        module_impls = Some(globals.implementations.clone());
 
         //merge impls
         merge_impl(&mut globals.implementations, &stored_impl);
@@ -1977,6 +2794,8 @@ pub fn import_module(
         );
     }
 
+    module_cache::store(globals, path, import_state, &mut start_context, module_impls);
+
     Ok(())
 }
 
@@ -15,7 +15,7 @@ use crate::value_storage::*;
 use errors::compiler_info::CompilerInfo;
 use ahash::AHashMap;
 
-use crate::compiler::compile_scope;
+use crate::compiler::{check_context_limit, compile_scope, context_limit_error};
 
 use internment::LocalIntern;
 use shared::StoredValue;
@@ -40,10 +40,24 @@ pub struct FunctionId {
 }
 
 pub type SyncPartId = usize;
+// sync a: the parts of a sync group never run at the same time, so they can share IDs
 #[derive(Clone)]
 pub struct SyncGroup {
-    pub parts: Vec<SyncPartId>,
-    pub groups_used: Vec<ArbitraryId>, // groups that are already used by this sync group, and can be reused in later parts
+    pub name: LocalIntern<String>,
+    pub def_area: CodeArea,
+    pub parts: Vec<(Value, String)>, // the value of each part, and how it's displayed
+    // the part each trigger function has run, since those all run at the same time
+    pub entered: AHashMap<Group, (SyncPartId, CodeArea)>,
+    pub uses: usize, // how many part blocks have been compiled
+}
+
+// f! starts the trigger function at the same time as the context it's called from
+#[derive(Clone)]
+pub struct FnCall {
+    pub target: Group,
+    pub from: Group,
+    pub order: f64,
+    pub area: CodeArea,
 }
 
 pub fn handle_operator(
@@ -316,6 +330,20 @@ pub fn execute_macro(
     globals: &mut Globals,
     parent: StoredValue,
     info: CompilerInfo,
+) -> Result<(), RuntimeError> {
+    // macros don't have names, so they are named after where they are defined
+    globals.profile_enter(|lines| format!("macro {}", lines.line_name(&m.def_file, m.arg_pos.0)));
+    let result = execute_macro_body((m, args), contexts, globals, parent, info);
+    globals.profile_exit();
+    result
+}
+
+fn execute_macro_body(
+    (m, args): (MacroFuncData, Vec<ast::Argument>),
+    contexts: &mut FullContext,
+    globals: &mut Globals,
+    parent: StoredValue,
+    info: CompilerInfo,
 ) -> Result<(), RuntimeError> {
     contexts.reset_return_vals(globals);
     globals.push_new_preserved();
@@ -364,6 +392,8 @@ pub fn execute_macro(
     //dbg!(&combinations);
 
     for (arg_values, full_context) in combinations {
+        // the pattern variables are shared by every argument and the return value
+        let mut bindings = PatternBindings::default();
         let mut new_variables: AHashMap<LocalIntern<String>, Vec<VariableData>> =
             Default::default();
         let context = full_context.inner();
@@ -404,18 +434,20 @@ pub fn execute_macro(
                                 file: m.def_file,
                             });
 
-                            if !val.pure_matches_pat(
+                            if !call_matches_pat(
+                                &val,
                                 &pat,
                                 &arg_def_info,
                                 globals,
                                 context.clone(),
+                                &mut bindings,
                             )? {
                                 let arg_info = info.clone().with_area(CodeArea {
                                     pos: arg.pos,
                                     ..info.position
                                 });
                                 return Err(RuntimeError::PatternMismatchError {
-                                    pattern: pat.to_str(globals),
+                                    pattern: bound_pattern_str(&pat, &bindings, &info, globals, context)?,
                                     val: val.get_type_str(globals),
                                     val_def: globals.get_area(arg_values[i]),
                                     pat_def: globals.get_area(t),
@@ -496,13 +528,13 @@ pub fn execute_macro(
                             file: m.def_file,
                         });
 
-                        if !val.pure_matches_pat(&pat, &arg_def_info, globals, context.clone())? {
+                        if !call_matches_pat(&val, &pat, &arg_def_info, globals, context.clone(), &mut bindings)? {
                             let arg_info = info.clone().with_area(CodeArea {
                                 pos: arg.pos,
                                 ..info.position
                             });
                             return Err(RuntimeError::PatternMismatchError {
-                                pattern: pat.to_str(globals),
+                                pattern: bound_pattern_str(&pat, &bindings, &info, globals, context)?,
                                 val: val.get_type_str(globals),
                                 val_def: globals.get_area(arg_values[i]),
                                 pat_def: globals.get_area(t),
@@ -589,7 +621,7 @@ Should be used like this: value.macro(arguments)",
                     None => {
                         return Err(RuntimeError::CustomError(create_error(
                                 info.clone(),
-                                &format!("Non-optional argument '{}' not satisfied!", arg.name),
+                                &format!("Non-optional argument '{}' not satisfied!", arg.display_name()),
                                 &[
                                     (CodeArea {pos: arg.position, file: m.def_file}, "Value defined as mandatory here (because no default was given)"),
                                     (info.position, "Argument not provided here")
@@ -611,6 +643,7 @@ Should be used like this: value.macro(arguments)",
             file: m.def_file,
             pos: (0, 0),
         });
+        new_info.enter_module(m.def_module.clone());
 
         let stored_path = globals.path;
         (*globals).path = m.def_file;
@@ -635,14 +668,17 @@ Should be used like this: value.macro(arguments)",
                         (*context.inner()).broken = None;
                         if let Some(pat) = m.ret_pattern {
                             //dbg!(&globals.stored_values[pat], &globals.stored_values[ret]);
-                            if !globals.stored_values[ret].clone().pure_matches_pat(
-                                &globals.stored_values[pat].clone(),
+                            let pat_val = globals.stored_values[pat].clone();
+                            if !call_matches_pat(
+                                &globals.stored_values[ret].clone(),
+                                &pat_val,
                                 &info,
                                 globals,
                                 context.inner().clone(),
+                                &mut bindings,
                             )? {
                                 return Err(RuntimeError::PatternMismatchError {
-                                    pattern: globals.stored_values[pat].clone().to_str(globals),
+                                    pattern: bound_pattern_str(&pat_val, &bindings, &info, globals, context.inner())?,
                                     val: globals.stored_values[ret].clone().to_str(globals),
                                     pat_def: globals.get_area(pat),
                                     val_def: globals.get_area(ret),
@@ -666,8 +702,18 @@ Should be used like this: value.macro(arguments)",
             }
         }
         //dbg!(out_contexts.len(), info.position);
+        if out_contexts.len() > 1 {
+            globals.trace_contexts(
+                &format!("macro returned in {} contexts", out_contexts.len()),
+                &info.position,
+            );
+            for c in &mut out_contexts {
+                c.inner().split_stack.push(info.position);
+            }
+        }
         if !out_contexts.is_empty() {
             *full_context = FullContext::stack(&mut out_contexts.into_iter()).unwrap();
+            check_context_limit(full_context, globals, &info)?;
         }
 
         for c in full_context.iter() {
@@ -681,10 +727,56 @@ Should be used like this: value.macro(arguments)",
     Ok(())
 }
 
+// checks a value given to (or returned by) a macro, binding the pattern variables for
+// the rest of the call
+fn call_matches_pat(
+    val: &Value,
+    pat: &Value,
+    info: &CompilerInfo,
+    globals: &mut Globals,
+    context: Context,
+    bindings: &mut PatternBindings,
+) -> Result<bool, RuntimeError> {
+    let outer = globals.pattern_bindings.replace(std::mem::take(bindings));
+    let matches = val.pure_matches_pat(pat, info, globals, context);
+    *bindings = std::mem::replace(&mut globals.pattern_bindings, outer).unwrap_or_default();
+    matches
+}
+
+// the pattern for a mismatch error, with what its variables were bound to
+fn bound_pattern_str(
+    pat: &Value,
+    bindings: &PatternBindings,
+    info: &CompilerInfo,
+    globals: &mut Globals,
+    context: &Context,
+) -> Result<String, RuntimeError> {
+    let out = pat.clone().to_str(globals);
+    let vars = match convert_type(pat, type_id!(pattern), info, globals, context)? {
+        Value::Pattern(p) => p.vars(),
+        _ => unreachable!(),
+    };
+    let mut bound = Vec::new();
+    for var in vars {
+        if let Some(t) = bindings.get(&var) {
+            let s = format!("?{} = {}", var, Value::Pattern(t.clone()).to_str(globals));
+            if !bound.contains(&s) {
+                bound.push(s);
+            }
+        }
+    }
+    Ok(if bound.is_empty() {
+        out
+    } else {
+        format!("{} (where {})", out, bound.join(", "))
+    })
+}
+
 pub fn reduce_combinations<'a, T, F>(
     a: Vec<T>,
     contexts: &'a mut FullContext,
     globals: &mut Globals,
+    info: &CompilerInfo,
     reduce: F,
 ) -> Result<Vec<(Vec<StoredValue>, &'a mut FullContext)>, RuntimeError>
 where
@@ -705,6 +797,10 @@ where
             new_out.extend(new_list);
         }
         out = new_out;
+        if globals.max_contexts.is_some_and(|max| out.len() > max) {
+            let mut contexts = out.into_iter().map(|(_, c)| c);
+            return Err(context_limit_error(&mut contexts, globals, info));
+        }
     }
     globals.pop_preserved();
 
@@ -722,6 +818,7 @@ pub fn all_combinations<'a>(
         a,
         contexts,
         globals,
+        &info.clone(),
         |e: &ast::Expression, ctx, list: Vec<StoredValue>, globals| {
             e.eval(ctx, globals, info.clone(), constant)?;
             let mut added = Vec::new();
@@ -875,6 +972,14 @@ impl ToTriggerFunc for ast::CompoundStatement {
             );
 
             if !carried_breaks.is_empty() {
+                globals.context_splits += carried_breaks.len();
+                globals.trace_contexts(
+                    &format!(
+                        "split into {} contexts by returning from inside a trigger function",
+                        carried_breaks.len() + 1
+                    ),
+                    &info.position,
+                );
                 prev_context = FullContext::Split(
                     prev_context.clone().into(),
                     FullContext::stack(&mut carried_breaks.into_iter())
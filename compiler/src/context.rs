@@ -27,6 +27,10 @@ pub struct Context {
     pub start_group: Group,
     pub func_id: FnIdPtr,
     pub fn_context_change_stack: Vec<CodeArea>,
+    // where this context was split from others, oldest first
+    pub split_stack: Vec<CodeArea>,
+    // the output level new objects and triggers go in (see $.output_level)
+    pub output_level: usize,
     variables: AHashMap<LocalIntern<String>, Vec<VariableData>>,
     pub return_value: StoredValue,
     pub return_value2: StoredValue,
@@ -40,10 +44,15 @@ pub enum FullContext {
 }
 
 impl FullContext {
+    // the root context pointer can only be set once the context has been
+    // moved into its final place, so callers have to call `set_as_root` after this
     pub fn new(globals: &Globals) -> Self {
-        let mut new = FullContext::Single(Context::new(globals));
-        new.inner().root_context_ptr = &mut new;
-        new
+        FullContext::Single(Context::new(globals))
+    }
+
+    pub fn set_as_root(&mut self) {
+        let ptr: *mut FullContext = self;
+        self.inner().root_context_ptr = ptr;
     }
     pub fn inner(&mut self) -> &mut Context {
         match self {
@@ -306,6 +315,8 @@ impl Context {
             broken: None,
 
             fn_context_change_stack: Vec::new(),
+            split_stack: Vec::new(),
+            output_level: 0,
             return_value: globals.NULL_STORAGE,
             return_value2: globals.NULL_STORAGE,
             root_context_ptr: std::ptr::null_mut(),
@@ -12,7 +12,10 @@ use crate::context::VariableData;
 use errors::compiler_info::CodeArea;
 
 use crate::context::FullContext;
-use crate::leveldata::GdObj;
+use crate::http_fixtures::HttpFixtures;
+use crate::leveldata::{GdObj, SyncIds};
+use crate::module_cache::ModuleCache;
+use crate::profiler::{Profiler, SourceLines};
 
 use crate::compiler_types::*;
 use crate::value::*;
@@ -22,10 +25,24 @@ use ahash::AHashMap;
 //use std::boxed::Box;
 use crate::value_storage::*;
 use errors::compiler_info::CompilerInfo;
+use parser::ast;
 
+#[cfg(not(target_arch = "wasm32"))]
+use rand::SeedableRng;
+#[cfg(not(target_arch = "wasm32"))]
+use rand_chacha::ChaCha8Rng;
 use std::io::Write;
 use std::path::PathBuf;
 
+// how far the level was built at some point, so everything added after it can be taken out
+// again (when a `try` body fails)
+pub struct LevelState {
+    fn_lens: Vec<usize>,
+    objects: usize,
+    fn_calls: usize,
+    sync_entered: Vec<AHashMap<Group, (SyncPartId, CodeArea)>>,
+}
+
 #[allow(non_snake_case)]
 pub struct Globals<'a> {
     //counters for arbitrary groups
@@ -43,6 +60,12 @@ pub struct Globals<'a> {
     pub type_id_count: u16,
 
     pub type_descriptions: AHashMap<u16, String>,
+    // the variants of the types that are defined as enums
+    pub enum_variants: AHashMap<u16, Vec<ast::EnumVariant>>,
+    // the members every type that matches the interface has to implement
+    pub interfaces: AHashMap<u16, Vec<LocalIntern<String>>>,
+    // the pattern variables bound by the macro call whose arguments are being checked
+    pub pattern_bindings: Option<PatternBindings>,
 
     pub func_ids: Vec<FunctionId>,
     pub objects: Vec<GdObj>,
@@ -50,6 +73,8 @@ pub struct Globals<'a> {
     pub initial_objects: Option<StoredValue>,
 
     pub prev_imports: AHashMap<ImportType, (StoredValue, Implementations)>,
+    // the names exported by every module that was compiled with `export` definitions
+    pub module_exports: AHashMap<ImportType, Vec<LocalIntern<String>>>,
 
     pub trigger_order: f64,
 
@@ -57,11 +82,55 @@ pub struct Globals<'a> {
     pub implementations: Implementations,
 
     pub sync_groups: Vec<SyncGroup>,
+    // every trigger function call, to find the sync parts that are started at the same time
+    pub fn_calls: Vec<FnCall>,
+    pub sync_ids: SyncIds,
     pub includes: Vec<PathBuf>,
 
     pub permissions: BuiltinPermissions,
 
+    // names of the object properties defined in the std library (obj_props)
+    pub obj_prop_names: AHashMap<u16, String>,
+
+    // modules from previous builds that can be reused
+    pub module_cache: &'a mut ModuleCache,
+    pub source_hashes: AHashMap<SpwnSource, Option<u64>>,
+    // the files each module that is currently being imported depends on
+    pub module_deps: Vec<Vec<SpwnSource>>,
+    // the files each finished import depended on
+    pub import_deps: AHashMap<ImportType, Vec<SpwnSource>>,
+    // number of builtins with side effects called so far (modules that call them can't be reused)
+    pub side_effects: usize,
+    // everything $.print wrote, so a reused module can print the same thing again
+    pub printed: String,
+
+    // only set when the build is being profiled
+    pub profiler: Option<Profiler>,
+    pub source_lines: SourceLines,
+    // the most contexts that can exist at once before the build is stopped, if any
+    pub max_contexts: Option<usize>,
+    // print every split and merge of the contexts
+    pub trace_contexts: bool,
+    // number of contexts that splits have made so far, to find the statement that made them
+    pub context_splits: usize,
+
+    // every $.random call comes from this, so builds with the same seed are the same
+    // (ChaCha8 gives the same numbers in every version of rand_chacha, unlike StdRng)
+    pub seed: u64,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub rng: ChaCha8Rng,
+    pub random_used: bool,
+
+    pub http_fixtures: Option<HttpFixtures>,
+    // answers for $.get_input, by key or prompt
+    pub inputs: AHashMap<String, String>,
+    // the values given with -D, which the script reads from $.defines
+    pub defines: AHashMap<LocalIntern<String>, Value>,
+    // names of the levels added with $.output_level, output level n is output_levels[n - 1]
+    pub output_levels: Vec<String>,
+
     pub TYPE_MEMBER_NAME: LocalIntern<String>,
+    pub VARIANT_MEMBER_NAME: LocalIntern<String>,
     pub SELF_MEMBER_NAME: LocalIntern<String>,
     pub OR_BUILTIN: LocalIntern<String>,
     pub AND_BUILTIN: LocalIntern<String>,
@@ -124,6 +193,16 @@ impl<'a> Globals<'a> {
         }
     }
 
+    // the members of the interface that the type doesn't implement
+    pub fn missing_members(&self, typ: u16, interface: u16) -> Vec<LocalIntern<String>> {
+        let implemented = self.implementations.get(&typ);
+        self.interfaces[&interface]
+            .iter()
+            .filter(|m| !implemented.is_some_and(|i| i.contains_key(*m)))
+            .copied()
+            .collect()
+    }
+
     pub fn get_type_str(&self, p: StoredValue) -> String {
         let val = &self.stored_values[p];
         let typ = match val {
@@ -147,6 +226,7 @@ impl<'a> Globals<'a> {
         permissions: BuiltinPermissions,
         initial_string: String,
         std_out: &'a mut impl Write,
+        module_cache: &'a mut ModuleCache,
     ) -> Self {
         let (storage, builtin_storage, null_storage) = ValStorage::new();
         let mut type_descriptions = AHashMap::<u16, String>::default();
@@ -242,6 +322,7 @@ Arrays are used to store lists of values at compile time.
             type_ids: AHashMap::default(),
 
             prev_imports: AHashMap::default(),
+            module_exports: AHashMap::default(),
             type_id_count: 0,
             trigger_order: 0.0,
             uid_counter: 0,
@@ -256,14 +337,34 @@ Arrays are used to store lists of values at compile time.
             objects: Vec::new(),
             initial_string,
             implementations: AHashMap::default(),
-            sync_groups: vec![SyncGroup {
-                parts: vec![0],
-                groups_used: Vec::new(),
-            }],
+            sync_groups: Vec::new(),
+            fn_calls: Vec::new(),
+            sync_ids: Default::default(),
             includes: Vec::new(),
 
             permissions,
+            obj_prop_names: AHashMap::default(),
+            module_cache,
+            source_hashes: AHashMap::default(),
+            module_deps: Vec::new(),
+            import_deps: AHashMap::default(),
+            side_effects: 0,
+            printed: String::new(),
+            profiler: None,
+            source_lines: SourceLines::default(),
+            max_contexts: None,
+            trace_contexts: false,
+            context_splits: 0,
+            seed: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            rng: ChaCha8Rng::seed_from_u64(0),
+            random_used: false,
+            http_fixtures: None,
+            inputs: AHashMap::default(),
+            defines: AHashMap::default(),
+            output_levels: Vec::new(),
             TYPE_MEMBER_NAME: LocalIntern::new(String::from("type")),
+            VARIANT_MEMBER_NAME: LocalIntern::new(String::from("variant")),
             SELF_MEMBER_NAME: LocalIntern::new(String::from("self")),
             BUILTIN_STORAGE: builtin_storage,
             NULL_STORAGE: null_storage,
@@ -275,6 +376,9 @@ Arrays are used to store lists of values at compile time.
             built_in_path: None,
             std_out,
             type_descriptions,
+            enum_variants: AHashMap::default(),
+            interfaces: AHashMap::default(),
+            pattern_bindings: None,
             initial_objects: None,
         };
 
@@ -350,6 +454,23 @@ Arrays are used to store lists of values at compile time.
     //         .map
     //         .retain(|a, _| -> bool { !removed.contains(a) });
     // }
+    pub fn set_obj_prop_names(&mut self, obj_props: StoredValue) {
+        if let Value::Dict(d) = &self.stored_values[obj_props] {
+            // some properties share an id, so the alphabetically first name is picked (it is inserted last)
+            let mut props = d.iter().collect::<Vec<_>>();
+            props.sort_by(|a, b| b.0.as_str().cmp(a.0.as_str()));
+            for (name, key) in props {
+                if let Value::Dict(key) = &self.stored_values[*key] {
+                    if let Some(Value::Number(id)) =
+                        key.get(&self.OBJ_KEY_ID).map(|id| &self.stored_values[*id])
+                    {
+                        self.obj_prop_names.insert(*id as u16, name.to_string());
+                    }
+                }
+            }
+        }
+    }
+
     pub fn push_new_preserved(&mut self) {
         self.stored_values.preserved_stack.push(Vec::new());
     }
@@ -367,6 +488,7 @@ Arrays are used to store lists of values at compile time.
     }
 
     pub fn collect_garbage(&mut self, contexts: &mut FullContext) {
+        self.profile_enter(|_| "garbage collection".to_string());
         //gc
         //println!("before: {}", self.stored_values.map.len());
 
@@ -436,5 +558,59 @@ Arrays are used to store lists of values at compile time.
         //println!("after: {}", self.stored_values.map.len());
 
         self.stored_values.prev_value_count = self.stored_values.map.len() as u32;
+        self.profile_exit();
+    }
+
+    // the name is only made when profiling
+    pub fn profile_enter(&mut self, name: impl FnOnce(&mut SourceLines) -> String) {
+        if let Some(profiler) = &mut self.profiler {
+            let name = name(&mut self.source_lines);
+            profiler.enter(name);
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.rng = ChaCha8Rng::seed_from_u64(seed);
+        }
+    }
+
+    pub fn level_state(&self) -> LevelState {
+        LevelState {
+            fn_lens: self.func_ids.iter().map(|f| f.obj_list.len()).collect(),
+            objects: self.objects.len(),
+            fn_calls: self.fn_calls.len(),
+            sync_entered: self.sync_groups.iter().map(|g| g.entered.clone()).collect(),
+        }
+    }
+
+    // removes the trigger functions, triggers, objects, calls and sync parts added since `state`.
+    // the arbitrary ids made since then aren't given out again, since they can still be stored
+    // in a variable
+    pub fn restore_level_state(&mut self, state: LevelState) {
+        self.func_ids.truncate(state.fn_lens.len());
+        for (f, len) in self.func_ids.iter_mut().zip(state.fn_lens) {
+            f.obj_list.truncate(len);
+        }
+        self.objects.truncate(state.objects);
+        self.fn_calls.truncate(state.fn_calls);
+        for (group, entered) in self.sync_groups.iter_mut().zip(state.sync_entered) {
+            group.entered = entered;
+        }
+    }
+
+    pub fn trace_contexts(&mut self, message: &str, area: &CodeArea) {
+        if self.trace_contexts {
+            let location = self.source_lines.location_name(area);
+            println!("[contexts] {} at {}", message, location);
+        }
+    }
+
+    pub fn profile_exit(&mut self) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit();
+        }
     }
 }
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+// answers $.http_request with recorded responses instead of going to the network,
+// so scripts that use it can be built offline (for example in tests)
+#[derive(Debug, Clone)]
+pub struct HttpFixtures {
+    pub dir: PathBuf,
+    // make the real requests and save their responses in `dir`
+    pub record: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub text: String,
+}
+
+impl HttpFixtures {
+    // one file for every method and url, like `get_example.com_api_3c8f2e1a9b0d4f67.json`
+    pub fn path(&self, method: &str, url: &str) -> PathBuf {
+        let readable = url
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .unwrap_or(url)
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .take(80)
+            .collect::<String>();
+        // the readable part can be the same for different urls
+        let hash = fnv1a(format!("{} {}", method, url).as_bytes());
+        self.dir
+            .join(format!("{}_{}_{:016x}.json", method, readable, hash))
+    }
+
+    pub fn load(&self, method: &str, url: &str) -> Result<HttpResponse, String> {
+        let path = self.path(method, url);
+        let content = fs::read_to_string(&path).map_err(|_| {
+            format!(
+                "No recorded response for {} {} (expected it in {}). Build with `--record-http` to record it",
+                method.to_uppercase(),
+                url,
+                path.display()
+            )
+        })?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid http fixture {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, response: &HttpResponse) -> Result<(), String> {
+        let path = self.path(&response.method, &response.url);
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&path, serde_json::to_string_pretty(response).unwrap()))
+            .map_err(|e| format!("Could not write http fixture {}: {}", path.display(), e))
+    }
+}
+
+// a hash that stays the same between rust versions, so fixture names don't change
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
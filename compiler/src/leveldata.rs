@@ -1,7 +1,7 @@
 // useful things for dealing with gd level data
 
 use crate::builtins::*;
-use crate::compiler_types::FunctionId;
+use crate::compiler_types::{FunctionId, SyncPartId};
 use crate::context::Context;
 use ahash::{AHashMap, AHashSet};
 use parser::ast::ObjectMode;
@@ -118,6 +118,8 @@ pub struct GdObj {
     pub params: AHashMap<u16, ObjParam>,
     pub mode: ObjectMode,
     pub unique_id: usize,
+    // the output level the object goes in (0 is the main level)
+    pub output: usize,
 }
 
 impl GdObj {
@@ -126,6 +128,22 @@ impl GdObj {
 
         (*self).clone()
     }
+
+    // the properties sorted by key, named with `prop_names` where they have a name.
+    // arbitrary ids show up as 0, so they should be resolved first
+    pub fn canonical(&self, prop_names: &AHashMap<u16, String>) -> String {
+        let mut param_list = self.params.iter().collect::<Vec<(&u16, &ObjParam)>>();
+        param_list.sort_by(|a, b| (*a.0).cmp(b.0));
+
+        param_list
+            .iter()
+            .map(|(key, param)| match prop_names.get(key) {
+                Some(name) => format!("{}: {}", name, param),
+                None => format!("{}: {}", key, param),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
 
 pub fn get_used_ids(ls: &str) -> [AHashSet<u16>; 4] {
@@ -235,15 +253,108 @@ pub fn remove_spwn_objects(file_content: &mut String) {
         .join(";");
 }
 
+// the sync group and part each arbitrary group, color, block and item ID was made in
+pub type SyncIds = [AHashMap<ArbitraryId, (usize, SyncPartId)>; 4];
+
 //returns the string to be appended to the old string
 pub fn append_objects(
     mut objects: Vec<GdObj>,
     old_ls: &str,
+    sync_ids: &SyncIds,
 ) -> Result<(String, [usize; 4]), String> {
+    let used_ids = resolve_ids(&mut objects.iter_mut().collect::<Vec<_>>(), old_ls, sync_ids)?;
+
+    fn serialize_obj(mut trigger: GdObj) -> String {
+        let mut obj_string = String::new();
+        match trigger.mode {
+            ObjectMode::Object => {
+                match trigger.params.get_mut(&57) {
+                    Some(ObjParam::GroupList(l)) => (*l).push(SPWN_SIGNATURE_GROUP),
+                    Some(ObjParam::Group(g)) => {
+                        let group = *g;
+                        trigger
+                            .params
+                            .insert(57, ObjParam::GroupList(vec![group, SPWN_SIGNATURE_GROUP]));
+                    }
+                    _ => {
+                        trigger
+                            .params
+                            .insert(57, ObjParam::Group(SPWN_SIGNATURE_GROUP));
+                    }
+                };
+
+                let mut param_list = trigger.params.iter().collect::<Vec<(&u16, &ObjParam)>>();
+
+                param_list.sort_by(|a, b| (*a.0).cmp(b.0));
+
+                for param in param_list {
+                    obj_string += &format!("{},{},", param.0, param.1);
+                }
+
+                obj_string + ";"
+            }
+            ObjectMode::Trigger => {
+                match trigger.params.get_mut(&57) {
+                    Some(ObjParam::GroupList(l)) => {
+                        (*l).push(SPWN_SIGNATURE_GROUP);
+                        //list
+                    }
+                    Some(ObjParam::Group(g)) => {
+                        let group = *g;
+                        trigger
+                            .params
+                            .insert(57, ObjParam::GroupList(vec![group, SPWN_SIGNATURE_GROUP]));
+                    }
+                    _ => {
+                        trigger
+                            .params
+                            .insert(57, ObjParam::Group(SPWN_SIGNATURE_GROUP));
+                        //Vec::new()
+                    }
+                };
+
+                /*let spawned = match trigger.params.get(&62) {
+                    Some(ObjParam::Bool(b)) => *b,
+                    _ => groups.iter().any(|x| x.id != ID::Specific(0)),
+                };
+
+                if spawned {
+                    obj_string += "87,1,";
+                }*/
+
+                let mut param_list = trigger.params.iter().collect::<Vec<(&u16, &ObjParam)>>();
+
+                param_list.sort_by(|a, b| (*a.0).cmp(b.0));
+
+                for param in param_list {
+                    obj_string += &format!("{},{},", param.0, param.1);
+                }
+                obj_string + "108,1;" //linked group
+            }
+        }
+    }
+
+    let mut full_obj_string = String::new();
+
+    for obj in objects {
+        full_obj_string += &serialize_obj(obj)
+    }
+    Ok((full_obj_string, used_ids))
+}
+
+//gives all the arbitrary ids in the objects specific ids that aren't used in the old level string,
+//returns the amount of groups, colors, block IDs and item IDs used in total
+//the properties of each object are resolved in order of their key, so the ids given to the level
+//are the same on every build (this is used both for the level and for `--emit triggers`)
+pub fn resolve_ids(
+    objects: &mut [&mut GdObj],
+    old_ls: &str,
+    sync_ids: &SyncIds,
+) -> Result<[usize; 4], String> {
     let mut closed_ids = get_used_ids(old_ls);
 
     //collect all specific ids mentioned into closed_[id] lists
-    for obj in &objects {
+    for obj in objects.iter() {
         for prop in obj.params.values() {
             let class_index;
             let id;
@@ -291,10 +402,21 @@ pub fn append_objects(
         AHashMap::default(),
     ];
 
+    // the IDs given to each sync group, and the ones each of its parts is using.
+    // sharing them here is enough: append_objects resolves every ID through this after the
+    // optimizer has run, and the groups the optimizer makes are newer than every ID made in a
+    // sync part, so they are never shared
+    let mut sync_pools: [AHashMap<usize, Vec<SpecificId>>; 4] = Default::default();
+    let mut sync_used: [AHashMap<(usize, SyncPartId), AHashSet<SpecificId>>; 4] =
+        Default::default();
+
     const ID_MAX: u16 = 9999;
 
-    for obj in &mut objects {
-        for prop in obj.params.values_mut() {
+    for obj in objects.iter_mut() {
+        // go through the properties in a fixed order so the same ids are picked every time
+        let mut params = obj.params.iter_mut().collect::<Vec<_>>();
+        params.sort_by_key(|(key, _)| **key);
+        for (_, prop) in params {
             let class_index;
             let ids: Vec<&mut Id>;
             match prop {
@@ -326,15 +448,29 @@ pub fn append_objects(
                         *id = Id::Specific(match id_maps[class_index].get(i) {
                             Some(a) => *a,
                             None => {
-                                let mut out = None;
-                                for i in 1..10000 {
-                                    if !closed_ids[class_index].contains(&i) {
-                                        out = Some(i);
-                                        closed_ids[class_index].insert(i);
-                                        break;
+                                let part = sync_ids[class_index].get(i).copied();
+                                // parts of a sync group never run at the same time, so an ID
+                                // can be reused by every part that isn't using it yet
+                                let shared = part.and_then(|(group, part)| {
+                                    let used = sync_used[class_index].entry((group, part)).or_default();
+                                    sync_pools[class_index]
+                                        .get(&group)?
+                                        .iter()
+                                        .find(|id| !used.contains(id))
+                                        .copied()
+                                });
+                                let out = shared.or_else(|| {
+                                    let free = (1..10000).find(|i| !closed_ids[class_index].contains(i))?;
+                                    closed_ids[class_index].insert(free);
+                                    if let Some((group, _)) = part {
+                                        sync_pools[class_index].entry(group).or_default().push(free);
                                     }
-                                }
+                                    Some(free)
+                                });
                                 if let Some(id) = out {
+                                    if let Some(part) = part {
+                                        sync_used[class_index].entry(part).or_default().insert(id);
+                                    }
                                     id_maps[class_index].insert(*i, id);
                                     id
                                 } else {
@@ -363,92 +499,12 @@ pub fn append_objects(
         }
     }
 
-    //println!("group_map: {:?}", id_maps[0]);
-
-    fn serialize_obj(mut trigger: GdObj) -> String {
-        let mut obj_string = String::new();
-        match trigger.mode {
-            ObjectMode::Object => {
-                match trigger.params.get_mut(&57) {
-                    Some(ObjParam::GroupList(l)) => (*l).push(SPWN_SIGNATURE_GROUP),
-                    Some(ObjParam::Group(g)) => {
-                        let group = *g;
-                        trigger
-                            .params
-                            .insert(57, ObjParam::GroupList(vec![group, SPWN_SIGNATURE_GROUP]));
-                    }
-                    _ => {
-                        trigger
-                            .params
-                            .insert(57, ObjParam::Group(SPWN_SIGNATURE_GROUP));
-                    }
-                };
-
-                let mut param_list = trigger.params.iter().collect::<Vec<(&u16, &ObjParam)>>();
-
-                param_list.sort_by(|a, b| (*a.0).cmp(b.0));
-
-                for param in param_list {
-                    obj_string += &format!("{},{},", param.0, param.1);
-                }
-
-                obj_string + ";"
-            }
-            ObjectMode::Trigger => {
-                match trigger.params.get_mut(&57) {
-                    Some(ObjParam::GroupList(l)) => {
-                        (*l).push(SPWN_SIGNATURE_GROUP);
-                        //list
-                    }
-                    Some(ObjParam::Group(g)) => {
-                        let group = *g;
-                        trigger
-                            .params
-                            .insert(57, ObjParam::GroupList(vec![group, SPWN_SIGNATURE_GROUP]));
-                    }
-                    _ => {
-                        trigger
-                            .params
-                            .insert(57, ObjParam::Group(SPWN_SIGNATURE_GROUP));
-                        //Vec::new()
-                    }
-                };
-
-                /*let spawned = match trigger.params.get(&62) {
-                    Some(ObjParam::Bool(b)) => *b,
-                    _ => groups.iter().any(|x| x.id != ID::Specific(0)),
-                };
-
-                if spawned {
-                    obj_string += "87,1,";
-                }*/
-
-                let mut param_list = trigger.params.iter().collect::<Vec<(&u16, &ObjParam)>>();
-
-                param_list.sort_by(|a, b| (*a.0).cmp(b.0));
-
-                for param in param_list {
-                    obj_string += &format!("{},{},", param.0, param.1);
-                }
-                obj_string + "108,1;" //linked group
-            }
-        }
-    }
-
-    let mut full_obj_string = String::new();
-
-    for obj in objects {
-        full_obj_string += &serialize_obj(obj)
-    }
-    Ok((
-        full_obj_string,
-        [
-            closed_ids[0].len(),
-            closed_ids[1].len(),
-            closed_ids[2].len(),
-            closed_ids[3].len(),
-        ],
-    ))
+    Ok([
+        closed_ids[0].len(),
+        closed_ids[1].len(),
+        closed_ids[2].len(),
+        closed_ids[3].len(),
+    ])
 }
 
 pub fn apply_fn_ids(func_ids: &[FunctionId]) -> Vec<GdObj> {
@@ -542,3 +598,78 @@ pub fn apply_fn_ids(func_ids: &[FunctionId]) -> Vec<GdObj> {
 
     full_obj_list
 }
+
+//human readable listing of the compiled triggers, grouped by function id
+pub fn trigger_listing(
+    func_ids: &[FunctionId],
+    objects: &[GdObj],
+    prop_names: &AHashMap<u16, String>,
+    sync_ids: &SyncIds,
+) -> Result<String, String> {
+    let mut func_ids = func_ids.to_vec();
+    let mut objects = objects.to_vec();
+
+    // resolve ids in the same order as they are added to the level
+    let mut to_resolve = func_ids
+        .iter_mut()
+        .flat_map(|f| f.obj_list.iter_mut())
+        .collect::<Vec<_>>();
+    to_resolve.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    let mut to_resolve = to_resolve
+        .into_iter()
+        .map(|(obj, _)| obj)
+        .chain(objects.iter_mut())
+        .collect::<Vec<_>>();
+    resolve_ids(&mut to_resolve, "", sync_ids)?;
+
+    let mut out = String::new();
+
+    for (i, func_id) in func_ids.iter().enumerate() {
+        out += &match func_id.parent {
+            Some(p) => format!("function {} (parent: function {})", i, p),
+            None => format!("function {} (top level)", i),
+        };
+        if func_id.obj_list.is_empty() {
+            out += ": no triggers\n";
+            continue;
+        }
+        out += ":\n";
+
+        let mut obj_list = func_id.obj_list.iter().collect::<Vec<_>>();
+        obj_list.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        for (obj, order) in obj_list {
+            out += &format!("    [order {}] {}\n", order.0, obj.canonical(prop_names));
+        }
+    }
+
+    if !objects.is_empty() {
+        out += "objects:\n";
+        for obj in &objects {
+            out += &format!("    {}\n", obj.canonical(prop_names));
+        }
+    }
+
+    Ok(out)
+}
+
+//every object that is added to the level (with the triggers placed like in the level string),
+//one per line with resolved ids and sorted properties. the lines are sorted too, so the result
+//only changes when the level does
+pub fn canonical_level(
+    func_ids: &[FunctionId],
+    objects: &[GdObj],
+    prop_names: &AHashMap<u16, String>,
+    sync_ids: &SyncIds,
+) -> Result<Vec<String>, String> {
+    let mut all = apply_fn_ids(func_ids);
+    all.extend(objects.iter().cloned());
+    resolve_ids(&mut all.iter_mut().collect::<Vec<_>>(), "", sync_ids)?;
+
+    let mut lines = all
+        .iter()
+        .map(|obj| obj.canonical(prop_names))
+        .collect::<Vec<_>>();
+    lines.sort();
+    Ok(lines)
+}
@@ -7,8 +7,11 @@ pub mod compiler;
 pub mod compiler_types;
 pub mod context;
 pub mod globals;
+pub mod http_fixtures;
 pub mod leveldata;
+pub mod module_cache;
 pub mod parse_levelstring;
+pub mod profiler;
 pub mod value;
 pub mod value_storage;
 
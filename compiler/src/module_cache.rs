@@ -0,0 +1,767 @@
+//! The watch mode cache, which reuses the output of imported modules between the rebuilds of
+//! `spwn build --watch` when nothing they depend on has changed
+//!
+//! It only lives in memory, so separate `spwn build` runs always compile every module.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use ahash::{AHashMap, AHashSet};
+use errors::compiler_info::CodeArea;
+use internment::LocalIntern;
+use parser::ast;
+use shared::{BreakType, ImportType, SpwnSource, StoredValue};
+
+use crate::builtins::{get_lib_file, Id};
+use crate::compiler_types::{FunctionId, Implementations, TriggerOrder, TypeId};
+use crate::context::FullContext;
+use crate::globals::Globals;
+use crate::leveldata::{GdObj, ObjParam};
+use crate::value::{Macro, Pattern, Value};
+use crate::value_storage::StoredValData;
+
+const GROUP: usize = 0;
+const COLOR: usize = 1;
+const BLOCK: usize = 2;
+const ITEM: usize = 3;
+
+// what a module returned: nothing, `return` without a value, or a value
+pub type ModuleOutput = Option<Option<StoredValue>>;
+
+#[derive(Default)]
+pub struct ModuleCache {
+    modules: AHashMap<(SpwnSource, bool), CachedModule>,
+    files: AHashSet<PathBuf>,
+    // how many modules were reused in the last build
+    pub reused: usize,
+}
+
+impl ModuleCache {
+    // all the files that have been imported
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.files.iter().cloned().collect()
+    }
+}
+
+// a type that was defined by the module
+#[derive(Clone)]
+struct CachedType {
+    name: String,
+    id: TypeId,
+    area: CodeArea,
+    desc: Option<String>,
+    variants: Vec<ast::EnumVariant>,
+    // the required members, if the type is an interface
+    interface: Option<Vec<LocalIntern<String>>>,
+}
+
+// everything a module added to the globals, with the ids it was built with
+struct CachedModule {
+    fingerprint: u64,
+    deps: Vec<(SpwnSource, u64)>,
+
+    closed: [u16; 4],
+    allocated: [u16; 4],
+    fn_start: usize,
+    uid_start: usize,
+    uid_count: usize,
+    order_start: f64,
+    order_count: f64,
+
+    values: Vec<(StoredValue, StoredValData)>,
+    fixed_values: [StoredValue; 2],
+    output: ModuleOutput,
+    // what the module printed, which is printed again when it's reused
+    printed: String,
+    implementations: Implementations,
+    lib: bool,
+    prev_imports: Vec<(ImportType, StoredValue, Implementations)>,
+
+    func_ids: Vec<FunctionId>,
+    top_triggers: Vec<(GdObj, TriggerOrder)>,
+    objects: Vec<GdObj>,
+    // the names of the output levels, which the objects' output levels index into
+    output_levels: Vec<String>,
+
+    types: Vec<CachedType>,
+    external_types: Vec<(TypeId, String)>,
+}
+
+// the state of the globals right before a module is executed
+pub struct ImportState {
+    key: (SpwnSource, bool),
+    fingerprint: u64,
+    deps_index: usize,
+    closed: [u16; 4],
+    type_count: TypeId,
+    fn_lens: Vec<usize>,
+    objects: usize,
+    uid: usize,
+    trigger_order: f64,
+    side_effects: usize,
+    printed: usize,
+    sync_groups: usize,
+    implementations: Option<Implementations>,
+    prev_imports: AHashSet<ImportType>,
+}
+
+enum Ref<'a> {
+    Id(usize, &'a mut Id),
+    Type(&'a mut TypeId),
+    Val(&'a mut StoredValue),
+}
+
+fn walk_value(val: &mut Value, f: &mut impl FnMut(Ref)) {
+    match val {
+        Value::Group(g) => f(Ref::Id(GROUP, &mut g.id)),
+        Value::Color(c) => f(Ref::Id(COLOR, &mut c.id)),
+        Value::Block(b) => f(Ref::Id(BLOCK, &mut b.id)),
+        Value::Item(i) => f(Ref::Id(ITEM, &mut i.id)),
+        Value::TriggerFunc(t) => f(Ref::Id(GROUP, &mut t.start_group.id)),
+        Value::Dict(d) => d.values_mut().for_each(|v| f(Ref::Val(v))),
+        Value::Array(a) => a.iter_mut().for_each(|v| f(Ref::Val(v))),
+        Value::Macro(Macro::FuncLike(m)) => {
+            for arg in &mut m.args {
+                if let Some(v) = &mut arg.default {
+                    f(Ref::Val(v))
+                }
+                if let Some(v) = &mut arg.pattern {
+                    f(Ref::Val(v))
+                }
+            }
+            if let Some(v) = &mut m.ret_pattern {
+                f(Ref::Val(v))
+            }
+            m.def_variables.values_mut().for_each(|v| f(Ref::Val(v)));
+        }
+        Value::Obj(params, _) => params.iter_mut().for_each(|(_, p)| walk_param(p, f)),
+        Value::TypeIndicator(t) => f(Ref::Type(t)),
+        Value::Pattern(p) => walk_pattern(p, f),
+        _ => (),
+    }
+}
+
+fn walk_pattern(p: &mut Pattern, f: &mut impl FnMut(Ref)) {
+    match p {
+        Pattern::Type(t) => f(Ref::Type(t)),
+        Pattern::Array(a) => a.iter_mut().for_each(|p| walk_pattern(p, f)),
+        Pattern::Dict(d) => d.values_mut().for_each(|p| walk_pattern(p, f)),
+        Pattern::Either(a, b) | Pattern::Both(a, b) => {
+            walk_pattern(a, f);
+            walk_pattern(b, f);
+        }
+        Pattern::Not(a) => walk_pattern(a, f),
+        Pattern::Eq(v)
+        | Pattern::NotEq(v)
+        | Pattern::MoreThan(v)
+        | Pattern::LessThan(v)
+        | Pattern::MoreOrEq(v)
+        | Pattern::LessOrEq(v)
+        | Pattern::In(v) => f(Ref::Val(v)),
+        Pattern::Macro { args, ret } => {
+            args.iter_mut().for_each(|p| walk_pattern(p, f));
+            walk_pattern(ret, f);
+        }
+        Pattern::Any | Pattern::Var(_) => (),
+    }
+}
+
+fn walk_param(p: &mut ObjParam, f: &mut impl FnMut(Ref)) {
+    match p {
+        ObjParam::Group(g) => f(Ref::Id(GROUP, &mut g.id)),
+        ObjParam::Color(c) => f(Ref::Id(COLOR, &mut c.id)),
+        ObjParam::Block(b) => f(Ref::Id(BLOCK, &mut b.id)),
+        ObjParam::Item(i) => f(Ref::Id(ITEM, &mut i.id)),
+        ObjParam::GroupList(l) => l.iter_mut().for_each(|g| f(Ref::Id(GROUP, &mut g.id))),
+        _ => (),
+    }
+}
+
+fn hash_of(x: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    x.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub(crate) fn counters(globals: &Globals) -> [u16; 4] {
+    [
+        globals.closed_groups,
+        globals.closed_colors,
+        globals.closed_blocks,
+        globals.closed_items,
+    ]
+}
+
+// changes when a sync group is defined or used, since the IDs made in sync parts are tracked
+fn sync_state(globals: &Globals) -> usize {
+    globals.sync_groups.iter().map(|g| g.uses + 1).sum()
+}
+
+fn type_names(globals: &Globals) -> AHashMap<TypeId, String> {
+    globals
+        .type_ids
+        .iter()
+        .map(|(name, (id, _))| (*id, name.clone()))
+        .collect()
+}
+
+// the current content of a source file
+pub(crate) fn read_source(source: &SpwnSource) -> Option<String> {
+    match source {
+        SpwnSource::File(path) => fs::read_to_string(path).ok(),
+        SpwnSource::BuiltIn(path) => get_lib_file(path)
+            .and_then(|f| f.contents_utf8())
+            .map(String::from),
+        SpwnSource::String(s) => Some(s.to_string()),
+    }
+}
+
+fn source_hash(globals: &mut Globals, source: &SpwnSource) -> Option<u64> {
+    if let Some(hash) = globals.source_hashes.get(source) {
+        return *hash;
+    }
+    let hash = read_source(source).map(hash_of);
+    globals.source_hashes.insert(source.clone(), hash);
+    hash
+}
+
+// everything outside of the module's own dependencies that can change what it produces
+pub fn fingerprint(
+    globals: &mut Globals,
+    path: &ImportType,
+    source: &SpwnSource,
+    content: &str,
+    output_level: usize,
+) -> u64 {
+    let lib = matches!(path, ImportType::Lib(_));
+    // the index of a level depends on the order the levels were added in
+    let output_level = output_level
+        .checked_sub(1)
+        .map(|i| globals.output_levels[i].clone());
+    if let SpwnSource::File(f) = source {
+        globals.module_cache.files.insert(f.clone());
+    }
+
+    let mut imports = globals.prev_imports.keys().map(hash_of).collect::<Vec<_>>();
+    imports.sort_unstable();
+
+    // scripts can use the implementations of whoever imports them
+    let mut impls = Vec::new();
+    if !lib {
+        let names = type_names(globals);
+        let visible = globals
+            .implementations
+            .iter()
+            .flat_map(|(t, imp)| imp.iter().map(move |(name, (v, _))| (*t, *name, *v)))
+            .collect::<Vec<_>>();
+        for (t, name, v) in visible {
+            let file = globals.get_area(v).file;
+            let file_hash = source_hash(globals, &file);
+            impls.push(hash_of((names.get(&t), name.as_str(), file_hash)));
+        }
+        impls.sort_unstable();
+    }
+
+    hash_of((
+        content,
+        source,
+        lib,
+        &globals.permissions,
+        imports,
+        impls,
+        &globals.includes,
+        &globals.built_in_path,
+        output_level,
+    ))
+}
+
+impl ImportState {
+    pub fn new(globals: &mut Globals, path: &ImportType, source: &SpwnSource, fingerprint: u64) -> Self {
+        let lib = matches!(path, ImportType::Lib(_));
+        globals.module_deps.push(vec![source.clone()]);
+        ImportState {
+            key: (source.clone(), lib),
+            fingerprint,
+            deps_index: globals.module_deps.len() - 1,
+            closed: counters(globals),
+            type_count: globals.type_id_count,
+            fn_lens: globals.func_ids.iter().map(|f| f.obj_list.len()).collect(),
+            objects: globals.objects.len(),
+            uid: globals.uid_counter,
+            trigger_order: globals.trigger_order,
+            side_effects: globals.side_effects,
+            printed: globals.printed.len(),
+            sync_groups: sync_state(globals),
+            implementations: if lib {
+                None
+            } else {
+                Some(globals.implementations.clone())
+            },
+            prev_imports: globals.prev_imports.keys().cloned().collect(),
+        }
+    }
+}
+
+// adds the dependencies of an import to the module that is importing it
+pub fn add_import_deps(globals: &mut Globals, path: &ImportType) {
+    if let Some(deps) = globals.import_deps.get(path).cloned() {
+        if let Some(frame) = globals.module_deps.last_mut() {
+            frame.extend(deps);
+        }
+    }
+}
+
+// saves what a module produced, so it can be reused in the next build.
+// `module_impls` are the implementations a library defined
+pub fn store(
+    globals: &mut Globals,
+    path: &ImportType,
+    state: ImportState,
+    start_context: &mut FullContext,
+    module_impls: Option<Implementations>,
+) {
+    let mut deps = Vec::new();
+    for source in globals.module_deps.drain(state.deps_index..).flatten() {
+        if !deps.contains(&source) {
+            deps.push(source);
+        }
+    }
+    if let Some(frame) = globals.module_deps.last_mut() {
+        frame.extend(deps.iter().cloned());
+    }
+    globals.import_deps.insert(path.clone(), deps.clone());
+
+    let key = state.key.clone();
+    match snapshot(globals, state, start_context, module_impls, deps) {
+        Some(module) => globals.module_cache.modules.insert(key, module),
+        None => globals.module_cache.modules.remove(&key),
+    };
+}
+
+fn snapshot(
+    globals: &mut Globals,
+    state: ImportState,
+    start_context: &mut FullContext,
+    module_impls: Option<Implementations>,
+    deps: Vec<SpwnSource>,
+) -> Option<CachedModule> {
+    if globals.side_effects != state.side_effects || sync_state(globals) != state.sync_groups {
+        return None;
+    }
+    let output = match start_context {
+        FullContext::Single(c) => match c.broken {
+            Some((BreakType::Macro(v, _), _)) => Some(v),
+            _ => None,
+        },
+        FullContext::Split(_, _) => return None,
+    };
+
+    let mut hashed_deps = Vec::new();
+    for source in deps {
+        let hash = source_hash(globals, &source)?;
+        hashed_deps.push((source, hash));
+    }
+
+    let implementations = match (module_impls, &state.implementations) {
+        (Some(imps), _) => imps,
+        (None, Some(before)) => {
+            let mut changed = Implementations::default();
+            for (t, imp) in &globals.implementations {
+                for (name, val) in imp {
+                    if before.get(t).and_then(|b| b.get(name)) != Some(val) {
+                        changed.entry(*t).or_default().insert(*name, *val);
+                    }
+                }
+            }
+            changed
+        }
+        (None, None) => Implementations::default(),
+    };
+
+    let prev_imports = globals
+        .prev_imports
+        .iter()
+        .filter(|(k, _)| !state.prev_imports.contains(k))
+        .map(|(k, (v, imps))| (k.clone(), *v, imps.clone()))
+        .collect::<Vec<_>>();
+
+    // copy every value the module's output can reach
+    let fixed_values = [globals.NULL_STORAGE, globals.BUILTIN_STORAGE];
+    let mut stack = Vec::new();
+    if let Some(Some(v)) = output {
+        stack.push(v);
+    }
+    for imps in std::iter::once(&implementations).chain(prev_imports.iter().map(|(_, _, i)| i)) {
+        stack.extend(imps.values().flat_map(|imp| imp.values().map(|(v, _)| *v)));
+    }
+    stack.extend(prev_imports.iter().map(|(_, v, _)| *v));
+
+    let mut seen = AHashSet::default();
+    let mut values = Vec::new();
+    while let Some(v) = stack.pop() {
+        if fixed_values.contains(&v) || !seen.insert(v) {
+            continue;
+        }
+        let data = globals.stored_values.map.get(v)?.clone();
+        walk_value(&mut data.val.clone(), &mut |r| {
+            if let Ref::Val(p) = r {
+                stack.push(*p)
+            }
+        });
+        values.push((v, data));
+    }
+
+    let fn_start = state.fn_lens.len();
+    let func_ids = globals.func_ids[fn_start..].to_vec();
+    for (i, len) in state.fn_lens.iter().enumerate().skip(1) {
+        // triggers added to functions from outside the module
+        if globals.func_ids[i].obj_list.len() != *len {
+            return None;
+        }
+    }
+    let top_triggers = globals.func_ids[0].obj_list[state.fn_lens[0]..].to_vec();
+    let objects = globals.objects[state.objects..].to_vec();
+
+    // anything that refers to ids from outside the module would be different in the next build
+    let names = type_names(globals);
+    let mut external_types = AHashMap::default();
+    let mut reusable = true;
+    let mut check = |r: Ref| match r {
+        Ref::Id(c, Id::Arbitrary(n)) if *n <= state.closed[c] => reusable = false,
+        Ref::Type(t) if *t <= state.type_count => match names.get(t) {
+            Some(name) => {
+                external_types.insert(*t, name.clone());
+            }
+            None => reusable = false,
+        },
+        _ => (),
+    };
+    for (_, data) in &values {
+        walk_value(&mut data.val.clone(), &mut check);
+        check(Ref::Id(GROUP, &mut data.fn_context.id.clone()));
+    }
+    for imps in std::iter::once(&implementations).chain(prev_imports.iter().map(|(_, _, i)| i)) {
+        for t in imps.keys() {
+            check(Ref::Type(&mut t.clone()));
+        }
+    }
+    let fn_ok = |f: usize| f == 0 || f >= fn_start;
+    for f in &func_ids {
+        if !f.parent.map(fn_ok).unwrap_or(true) {
+            return None;
+        }
+    }
+    let all_objects = func_ids
+        .iter()
+        .flat_map(|f| f.obj_list.iter().map(|(o, _)| o))
+        .chain(top_triggers.iter().map(|(o, _)| o))
+        .chain(objects.iter());
+    for obj in all_objects {
+        if !fn_ok(obj.func_id) {
+            return None;
+        }
+        for param in obj.params.values() {
+            walk_param(&mut param.clone(), &mut check);
+        }
+    }
+    if !reusable {
+        return None;
+    }
+
+    let mut types = globals
+        .type_ids
+        .iter()
+        .filter(|(_, (id, _))| *id > state.type_count)
+        .map(|(name, (id, area))| CachedType {
+            name: name.clone(),
+            id: *id,
+            area: *area,
+            desc: globals.type_descriptions.get(id).cloned(),
+            variants: globals.enum_variants.get(id).cloned().unwrap_or_default(),
+            interface: globals.interfaces.get(id).cloned(),
+        })
+        .collect::<Vec<_>>();
+    types.sort_by_key(|t| t.id);
+
+    let closed = counters(globals);
+    Some(CachedModule {
+        fingerprint: state.fingerprint,
+        deps: hashed_deps,
+
+        closed: state.closed,
+        allocated: [0, 1, 2, 3].map(|c| closed[c] - state.closed[c]),
+        fn_start,
+        uid_start: state.uid,
+        uid_count: globals.uid_counter - state.uid,
+        order_start: state.trigger_order,
+        order_count: globals.trigger_order - state.trigger_order,
+
+        values,
+        fixed_values,
+        output,
+        printed: globals.printed[state.printed..].to_string(),
+        implementations,
+        lib: state.implementations.is_none(),
+        prev_imports,
+
+        func_ids,
+        top_triggers,
+        objects,
+        output_levels: globals.output_levels.clone(),
+
+        types,
+        external_types: external_types.into_iter().collect(),
+    })
+}
+
+// maps the ids a module was built with to the ids it gets in this build
+struct Remap {
+    closed_before: [u16; 4],
+    closed_now: [u16; 4],
+    fn_start: usize,
+    fn_now: usize,
+    uid_start: usize,
+    uid_now: usize,
+    order_start: f64,
+    order_now: f64,
+    // the output level every level the module was built with has in this build
+    output_levels: Vec<usize>,
+    types: AHashMap<TypeId, TypeId>,
+    keys: AHashMap<StoredValue, StoredValue>,
+}
+
+impl Remap {
+    fn apply(&self, r: Ref) {
+        match r {
+            Ref::Id(c, Id::Arbitrary(n)) if *n > self.closed_before[c] => {
+                *n = *n - self.closed_before[c] + self.closed_now[c]
+            }
+            Ref::Type(t) => {
+                if let Some(new) = self.types.get(t) {
+                    *t = *new
+                }
+            }
+            Ref::Val(v) => {
+                if let Some(new) = self.keys.get(v) {
+                    *v = *new
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn fn_id(&self, f: usize) -> usize {
+        if f >= self.fn_start {
+            f - self.fn_start + self.fn_now
+        } else {
+            f
+        }
+    }
+
+    fn trigger(&self, (obj, order): &(GdObj, TriggerOrder)) -> (GdObj, TriggerOrder) {
+        let order = if order.0 > self.order_start {
+            order.0 - self.order_start + self.order_now
+        } else {
+            order.0
+        };
+        (self.obj(obj), TriggerOrder(order))
+    }
+
+    fn obj(&self, obj: &GdObj) -> GdObj {
+        let mut obj = obj.clone();
+        obj.func_id = self.fn_id(obj.func_id);
+        obj.output = self.output_levels[obj.output];
+        if obj.unique_id > self.uid_start {
+            obj.unique_id = obj.unique_id - self.uid_start + self.uid_now;
+        }
+        for param in obj.params.values_mut() {
+            walk_param(param, &mut |r| self.apply(r));
+        }
+        obj
+    }
+
+    fn implementations(&self, imps: &Implementations) -> Implementations {
+        imps.iter()
+            .map(|(t, imp)| {
+                let mut t = *t;
+                self.apply(Ref::Type(&mut t));
+                let imp = imp
+                    .iter()
+                    .map(|(name, (v, in_scope))| {
+                        let mut v = *v;
+                        self.apply(Ref::Val(&mut v));
+                        (*name, (v, *in_scope))
+                    })
+                    .collect();
+                (t, imp)
+            })
+            .collect()
+    }
+}
+
+// adds the cached output of a module to the globals if nothing it depends on has changed.
+// returns what the module returned
+pub fn reuse(
+    globals: &mut Globals,
+    path: &ImportType,
+    source: &SpwnSource,
+    fingerprint: u64,
+) -> Option<ModuleOutput> {
+    let key = (source.clone(), matches!(path, ImportType::Lib(_)));
+    // outdated entries are replaced after the module is executed again
+    let module = globals.module_cache.modules.remove(&key)?;
+    if module.fingerprint != fingerprint {
+        return None;
+    }
+    for (source, hash) in &module.deps {
+        if source_hash(globals, source) != Some(*hash) {
+            return None;
+        }
+    }
+    // ids printed with their numbers (like with $.serialize) would show the ones from the old build
+    if !module.printed.is_empty() && module.closed != counters(globals) {
+        return None;
+    }
+
+    let mut types = AHashMap::default();
+    for (old, name) in &module.external_types {
+        types.insert(*old, globals.type_ids.get(name)?.0);
+    }
+    let mut type_count = globals.type_id_count;
+    let mut new_types = Vec::new();
+    for typ in &module.types {
+        match globals.type_ids.get(&typ.name) {
+            Some((id, def_area)) if *def_area == typ.area => {
+                types.insert(typ.id, *id);
+            }
+            // defined somewhere else, so the module has to report the error
+            Some(_) => return None,
+            None => {
+                type_count += 1;
+                types.insert(typ.id, type_count);
+                new_types.push(CachedType {
+                    id: type_count,
+                    ..typ.clone()
+                });
+            }
+        }
+    }
+    for typ in new_types {
+        globals.type_ids.insert(typ.name, (typ.id, typ.area));
+        if let Some(desc) = typ.desc {
+            globals.type_descriptions.insert(typ.id, desc);
+        }
+        if !typ.variants.is_empty() {
+            globals.enum_variants.insert(typ.id, typ.variants);
+        }
+        if let Some(members) = typ.interface {
+            globals.interfaces.insert(typ.id, members);
+        }
+    }
+    globals.type_id_count = type_count;
+
+    // the levels are found by name, since they can be added in another order in this build
+    let mut output_levels = vec![0];
+    for name in &module.output_levels {
+        let level = match globals.output_levels.iter().position(|l| l == name) {
+            Some(i) => i + 1,
+            None => {
+                globals.output_levels.push(name.clone());
+                globals.output_levels.len()
+            }
+        };
+        output_levels.push(level);
+    }
+
+    let mut remap = Remap {
+        closed_before: module.closed,
+        closed_now: counters(globals),
+        fn_start: module.fn_start,
+        fn_now: globals.func_ids.len(),
+        uid_start: module.uid_start,
+        uid_now: globals.uid_counter,
+        order_start: module.order_start,
+        order_now: globals.trigger_order,
+        output_levels,
+        types,
+        keys: AHashMap::default(),
+    };
+
+    remap.keys.insert(module.fixed_values[0], globals.NULL_STORAGE);
+    remap.keys.insert(module.fixed_values[1], globals.BUILTIN_STORAGE);
+    for (old, data) in &module.values {
+        let new = globals.stored_values.map.insert(data.clone());
+        remap.keys.insert(*old, new);
+    }
+    for (old, _) in &module.values {
+        let data = globals.stored_values.map.get_mut(remap.keys[old]).unwrap();
+        walk_value(&mut data.val, &mut |r| remap.apply(r));
+        remap.apply(Ref::Id(GROUP, &mut data.fn_context.id));
+    }
+
+    let implementations = remap.implementations(&module.implementations);
+    if module.lib {
+        // the importing module's implementations take precedence
+        let mut merged = implementations;
+        for (t, imp) in globals.implementations.drain() {
+            merged.entry(t).or_default().extend(imp);
+        }
+        globals.implementations = merged;
+    } else {
+        for (t, imp) in implementations {
+            globals.implementations.entry(t).or_default().extend(imp);
+        }
+    }
+
+    for (import, v, imps) in &module.prev_imports {
+        let mut v = *v;
+        remap.apply(Ref::Val(&mut v));
+        let imps = remap.implementations(imps);
+        globals.prev_imports.insert(import.clone(), (v, imps));
+        globals.import_deps.insert(
+            import.clone(),
+            module.deps.iter().map(|(s, _)| s.clone()).collect(),
+        );
+    }
+
+    for f in &module.func_ids {
+        globals.func_ids.push(FunctionId {
+            parent: f.parent.map(|p| remap.fn_id(p)),
+            width: f.width,
+            obj_list: f.obj_list.iter().map(|t| remap.trigger(t)).collect(),
+        });
+    }
+    for t in &module.top_triggers {
+        let t = remap.trigger(t);
+        globals.func_ids[0].obj_list.push(t);
+    }
+    for obj in &module.objects {
+        let obj = remap.obj(obj);
+        globals.objects.push(obj);
+    }
+
+    let closed = [0, 1, 2, 3].map(|c| remap.closed_now[c] + module.allocated[c]);
+    globals.closed_groups = closed[GROUP];
+    globals.closed_colors = closed[COLOR];
+    globals.closed_blocks = closed[BLOCK];
+    globals.closed_items = closed[ITEM];
+    globals.uid_counter += module.uid_count;
+    globals.trigger_order += module.order_count;
+
+    let deps = module.deps.iter().map(|(s, _)| s.clone()).collect::<Vec<_>>();
+    if let Some(frame) = globals.module_deps.last_mut() {
+        frame.extend(deps.iter().cloned());
+    }
+    globals.import_deps.insert(path.clone(), deps);
+
+    write!(globals.std_out, "{}", module.printed).expect("Error writing to output");
+    globals.printed += &module.printed;
+
+    let output = module.output.map(|v| v.map(|v| remap.keys[&v]));
+    globals.module_cache.reused += 1;
+    globals.module_cache.modules.insert(key, module);
+    Some(output)
+}
+
@@ -0,0 +1,174 @@
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use ahash::AHashMap;
+use errors::compiler_info::CodeArea;
+use shared::SpwnSource;
+
+use crate::module_cache::read_source;
+
+#[derive(Default, Clone, Copy)]
+struct Entry {
+    time: Duration,
+    calls: usize,
+}
+
+struct Frame {
+    name: String,
+    start: Instant,
+    children: Duration,
+}
+
+// records the time spent in every macro call, import and garbage collection,
+// keyed by the whole stack of frames above it
+#[derive(Default)]
+pub struct Profiler {
+    stack: Vec<Frame>,
+    // self time and call count for every stack ("a;b;c")
+    entries: AHashMap<String, Entry>,
+    // number of new contexts created by statements in every stack
+    splits: AHashMap<String, usize>,
+}
+
+// byte offsets where the lines of every source start, for naming code locations
+#[derive(Default)]
+pub struct SourceLines {
+    line_starts: AHashMap<SpwnSource, Vec<usize>>,
+}
+
+impl SourceLines {
+    // (line, column), both starting at 1
+    pub fn line_col(&mut self, source: &SpwnSource, pos: usize) -> (usize, usize) {
+        let starts = self.line_starts.entry(source.clone()).or_insert_with(|| {
+            let content = read_source(source).unwrap_or_default();
+            std::iter::once(0)
+                .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+                .collect()
+        });
+        let line = starts.partition_point(|start| *start <= pos);
+        (line, pos - starts[line - 1] + 1)
+    }
+
+    // file:line
+    pub fn line_name(&mut self, source: &SpwnSource, pos: usize) -> String {
+        format!("{}:{}", source_name(source), self.line_col(source, pos).0)
+    }
+
+    // file:line:column
+    pub fn location_name(&mut self, area: &CodeArea) -> String {
+        let (line, col) = self.line_col(&area.file, area.pos.0);
+        format!("{}:{}:{}", source_name(&area.file), line, col)
+    }
+}
+
+pub fn source_name(source: &SpwnSource) -> String {
+    match source {
+        SpwnSource::File(path) | SpwnSource::BuiltIn(path) => path.display().to_string(),
+        SpwnSource::String(_) => "<source>".to_string(),
+    }
+}
+
+impl Profiler {
+    pub fn enter(&mut self, name: String) {
+        self.stack.push(Frame {
+            // ; separates the frames in the folded format
+            name: name.replace(';', ":"),
+            start: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    pub fn exit(&mut self) {
+        let key = self.stack_key();
+        let frame = self.stack.pop().expect("exited more frames than were entered");
+        let elapsed = frame.start.elapsed();
+
+        let entry = self.entries.entry(key).or_default();
+        entry.time += elapsed.saturating_sub(frame.children);
+        entry.calls += 1;
+
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += elapsed;
+        }
+    }
+
+    // new contexts that were created directly in the current frame
+    pub fn record_splits(&mut self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let key = self.stack_key();
+        *self.splits.entry(key).or_default() += count;
+    }
+
+    fn stack_key(&self) -> String {
+        self.stack
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
+    // one line per stack with its self time in microseconds, which can be turned
+    // into a flamegraph with tools like inferno or flamegraph.pl
+    pub fn folded(&self) -> String {
+        let mut lines = self
+            .entries
+            .iter()
+            .filter(|(_, e)| e.time.as_micros() > 0)
+            .map(|(stack, e)| format!("{} {}", stack, e.time.as_micros()))
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.join("\n") + "\n"
+    }
+
+    // the frames that took the most time in total (including the frames they called)
+    pub fn summary(&self, count: usize) -> String {
+        let mut totals = AHashMap::<&str, Entry>::default();
+        for (stack, e) in &self.entries {
+            let frames = stack.split(';').collect::<Vec<_>>();
+            let mut seen = Vec::new();
+            for (i, frame) in frames.iter().enumerate() {
+                // recursive calls shouldn't count the same time twice
+                if seen.contains(frame) {
+                    continue;
+                }
+                seen.push(frame);
+                let total = totals.entry(frame).or_default();
+                total.time += e.time;
+                if i == frames.len() - 1 {
+                    total.calls += e.calls;
+                }
+            }
+        }
+        let mut totals = totals.into_iter().collect::<Vec<_>>();
+        totals.sort_by(|a, b| b.1.time.cmp(&a.1.time).then(a.0.cmp(b.0)));
+
+        let mut out = String::new();
+        for (name, e) in totals.iter().take(count) {
+            writeln!(
+                out,
+                "{:>10.2}ms {:>8} calls  {}",
+                e.time.as_secs_f64() * 1000.0,
+                e.calls,
+                name
+            )
+            .unwrap();
+        }
+
+        if !self.splits.is_empty() {
+            let mut splits = AHashMap::<&str, usize>::default();
+            for (stack, n) in &self.splits {
+                let innermost = stack.rsplit(';').next().unwrap_or_default();
+                *splits.entry(innermost).or_default() += n;
+            }
+            let mut splits = splits.into_iter().collect::<Vec<_>>();
+            splits.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            writeln!(out, "\ncontext splits:").unwrap();
+            for (name, n) in splits.iter().take(count) {
+                writeln!(out, "{:>10} new contexts  {}", n, name).unwrap();
+            }
+        }
+        out
+    }
+}
@@ -2,6 +2,7 @@ use crate::builtins::*;
 use crate::compiler::import_module;
 use crate::compiler::merge_all_contexts;
 use crate::compiler::compile_scope;
+use crate::compiler::check_context_limit;
 
 use errors::compiler_info::CodeArea;
 use errors::compiler_info::CompilerInfo;
@@ -54,6 +55,8 @@ pub struct MacroFuncData {
     pub args: Vec<MacroArgDef>,
     pub def_variables: AHashMap<LocalIntern<String>, StoredValue>,
     pub def_file: LocalIntern<SpwnSource>,
+    // the library the macro was defined in, which decides what builtins it can use
+    pub def_module: String,
     pub body: Vec<ast::Statement>,
     pub tag: ast::Attribute,
     pub arg_pos: FileRange,
@@ -76,6 +79,7 @@ impl Hash for Macro {
                     i.hash(state);
                 }
                 m.def_file.hash(state);
+                m.def_module.hash(state);
                 //body.hash(state);
                 //tag.hash(state);
                 m.arg_pos.hash(state);
@@ -103,6 +107,17 @@ pub struct MacroArgDef {
     pub position: FileRange,
     pub arg_type: ast::ArgType,
 }
+
+impl MacroArgDef {
+    // destructured arguments have a hidden name, so they're shown as `_`
+    pub fn display_name(&self) -> &str {
+        if self.name.starts_with('#') {
+            "_"
+        } else {
+            &self.name
+        }
+    }
+}
 // impl Macro {
 //     pub fn get_arg_area(&self) -> CodeArea {
 //         assert!(!self.args.is_empty());
@@ -143,9 +158,15 @@ pub enum Pattern {
     Macro {
         args: Vec<Pattern>, 
         ret: Box<Pattern>
-    }
+    },
+
+    // ?T, matches the type it was bound to in the current macro call
+    Var(LocalIntern<String>),
 }
 
+// the shape each pattern variable was bound to, like `@number` or `[[@string]]`
+pub type PatternBindings = AHashMap<LocalIntern<String>, Pattern>;
+
 pub fn pattern_from_value_variant(s: &str) -> Pattern {
     match s {
         "Group" => Pattern::Type(type_id!(group)),
@@ -174,12 +195,23 @@ impl Pattern {
     pub fn in_pat(
         &self,
         p: &Pattern,
-        globals: &Globals
+        globals: &mut Globals
     ) -> Result<bool, RuntimeError> {
         Ok( match (self, p) {
             (_, Pattern::Any) => true,
             (Pattern::Any, _) => false,
 
+            (Pattern::Var(a), Pattern::Var(b)) => a == b,
+            // in a macro call, the variable is bound to the first pattern it's compared with
+            (_, Pattern::Var(name)) => match globals.pattern_bindings.as_ref().map(|b| b.get(name).cloned()) {
+                Some(Some(bound)) => self.in_pat(&bound, globals)?,
+                Some(None) => {
+                    globals.pattern_bindings.as_mut().unwrap().insert(*name, self.clone());
+                    true
+                }
+                None => true,
+            },
+
             (_, Pattern::Not(a)) => !(self.in_pat(a, globals)?),
             (Pattern::Not(a), _) => !(a.in_pat(p, globals)?),
 
@@ -209,13 +241,49 @@ impl Pattern {
             }
             
             (_, Pattern::Both(a, b)) => self.in_pat(a, globals)? && self.in_pat(b, globals)?,
-            (_, Pattern::Either(a, b)) => self.in_pat(a, globals)? || self.in_pat(b, globals)?,
+            (_, Pattern::Either(a, b)) => {
+                // the variables bound by a branch that didn't match are unbound again
+                let saved = globals.pattern_bindings.clone();
+                self.in_pat(a, globals)? || {
+                    globals.pattern_bindings = saved;
+                    self.in_pat(b, globals)?
+                }
+            }
             // TODO: macro patterns??
             _ => false,
 
         } )
     }
 
+    // the shape both patterns have, if they're the same apart from the element type of
+    // empty arrays, which isn't known
+    pub fn unify(&self, other: &Pattern) -> Option<Pattern> {
+        match (self, other) {
+            (Pattern::Type(a), Pattern::Type(b)) if a == b => Some(self.clone()),
+            (Pattern::Array(a), Pattern::Array(b)) => match (a.first(), b.first()) {
+                (_, None) => Some(self.clone()),
+                (None, _) => Some(other.clone()),
+                (Some(a), Some(b)) => Some(Pattern::Array(vec![a.unify(b)?])),
+            },
+            _ => None,
+        }
+    }
+
+    // the pattern variables in the pattern
+    pub fn vars(&self) -> Vec<LocalIntern<String>> {
+        match self {
+            Pattern::Var(name) => vec![*name],
+            Pattern::Array(a) => a.iter().flat_map(|p| p.vars()).collect(),
+            Pattern::Dict(d) => d.values().flat_map(|p| p.vars()).collect(),
+            Pattern::Either(a, b) | Pattern::Both(a, b) => [a.vars(), b.vars()].concat(),
+            Pattern::Not(a) => a.vars(),
+            Pattern::Macro { args, ret } => {
+                args.iter().flat_map(|p| p.vars()).chain(ret.vars()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
     pub fn hash<H: std::hash::Hasher>(&self, state: &mut H, globals: &Globals) {
         match self {
             Pattern::Type(v) => v.hash(state),
@@ -240,6 +308,7 @@ impl Pattern {
             },
             Pattern::Not(p) => p.hash(state, globals),
             Pattern::Any => "anypat".hash(state),
+            Pattern::Var(name) => name.hash(state),
             Pattern::Eq(v) => globals.stored_values[*v].hash(state, globals),
             Pattern::NotEq(v) => globals.stored_values[*v].hash(state, globals),
             Pattern::MoreThan(v) => globals.stored_values[*v].hash(state, globals),
@@ -502,6 +571,28 @@ impl Value {
         }
     }
 
+    // what a pattern variable is bound to when it matches this value: the type, or for
+    // arrays with elements of one shape, an array of that shape
+    pub fn pattern_shape(&self, globals: &Globals) -> Pattern {
+        match self {
+            Value::Array(arr) => {
+                let mut elements: Option<Pattern> = None;
+                for el in arr {
+                    let shape = globals.stored_values[*el].pattern_shape(globals);
+                    elements = match elements {
+                        None => Some(shape),
+                        Some(prev) => match prev.unify(&shape) {
+                            Some(unified) => Some(unified),
+                            None => return Pattern::Type(type_id!(array)),
+                        },
+                    };
+                }
+                Pattern::Array(elements.into_iter().collect())
+            }
+            _ => Pattern::Type(self.to_num(globals)),
+        }
+    }
+
     // pub fn direct_references(&self) -> Vec<StoredValue> {
     //     match self {
     //         Value::Array(a) => {
@@ -609,6 +700,8 @@ impl Value {
             };
             match pat {
                 Pattern::Either(p1, p2) => {
+                    // the variables bound by a branch that didn't match are unbound again
+                    let saved = globals.pattern_bindings.clone();
                     self.matches_pat(
                         &Value::Pattern(*p1),
                         info,
@@ -620,6 +713,7 @@ impl Value {
                         match globals.stored_values[full_context.inner().return_value] {
                             Value::Bool(b) => {
                                 if !b {
+                                    globals.pattern_bindings = saved.clone();
                                     self.matches_pat(
                                         &Value::Pattern(p2.as_ref().clone()),
                                         info,
@@ -693,8 +787,38 @@ impl Value {
                     }
                 }
                 Pattern::Type(t) => {
+                    let typ = self.to_num(globals);
+                    // interfaces match every type that implements all of their members
+                    let matches = typ == t
+                        || (globals.interfaces.contains_key(&t)
+                            && globals.missing_members(typ, t).is_empty());
                     (*full_context.inner()).return_value = store_const_value(
-                        Value::Bool(self.to_num(globals) == t),
+                        Value::Bool(matches),
+                        globals,
+                        full_context.inner().start_group,
+                        info.position,
+                    );
+                }
+                Pattern::Var(name) => {
+                    let shape = self.pattern_shape(globals);
+                    // outside of macro calls every use of the variable is unrelated
+                    let matches = match globals.pattern_bindings.as_ref().map(|b| b.get(&name).cloned()) {
+                        Some(Some(bound)) => match bound.unify(&shape) {
+                            Some(unified) => {
+                                globals.pattern_bindings.as_mut().unwrap().insert(name, unified);
+                                true
+                            }
+                            // bound to a pattern by a macro argument
+                            None => shape.in_pat(&bound, globals)?,
+                        },
+                        Some(None) => {
+                            globals.pattern_bindings.as_mut().unwrap().insert(name, shape);
+                            true
+                        }
+                        None => true,
+                    };
+                    full_context.inner().return_value = store_const_value(
+                        Value::Bool(matches),
                         globals,
                         full_context.inner().start_group,
                         info.position,
@@ -1032,7 +1156,10 @@ impl Value {
                     out += "::";
                 }
                 out += "{";
-                let mut d_iter = d.iter();
+                // sorted, so the same dictionary is always shown the same way
+                let mut entries = d.iter().collect::<Vec<_>>();
+                entries.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+                let mut d_iter = entries.into_iter();
                 for (count, (key, val)) in (&mut d_iter).enumerate() {
                     if count > MAX_DICT_EL_DISPLAY {
                         let left = d_iter.count();
@@ -1071,7 +1198,7 @@ impl Value {
                         let mut out = String::from("(");
                         if !m.args.is_empty() {
                             for arg in m.args.iter() {
-                                out += &arg.name;
+                                out += arg.display_name();
                                 if let Some(val) = arg.pattern {
                                     out += &format!(
                                         ": {}",
@@ -1202,6 +1329,7 @@ impl Value {
                 Pattern::Any => {
                     "_".to_string()
                 }
+                Pattern::Var(name) => format!("?{}", name),
                 Pattern::Macro { args, ret } => {
                     let mut out = String::from("(");
                     if !args.is_empty() {
@@ -1582,6 +1710,135 @@ pub fn slice_array(
 use crate::compiler_types::EvalExpression;
 use crate::compiler_types::ToTriggerFunc;
 
+// `@type::variant` or `@type::variant(a, b)` as a match case, for a variant of an enum type
+struct VariantPattern {
+    typ: TypeId,
+    variant: LocalIntern<String>,
+    // the names the fields are bound to, `_` skips a field
+    bindings: Vec<LocalIntern<String>>,
+}
+
+fn variant_pattern(pat: &ast::Expression, globals: &Globals) -> Option<VariantPattern> {
+    let var = match &pat.values[..] {
+        [var] if pat.operators.is_empty() && var.operator.is_none() => var,
+        _ => return None,
+    };
+    let typ = match &var.value.body {
+        ast::ValueBody::TypeIndicator(name) => globals.type_ids.get(name)?.0,
+        _ => return None,
+    };
+    let (variant, args) = match &var.path[..] {
+        [ast::Path::Associated(v)] => (*v, &[][..]),
+        [ast::Path::Associated(v), ast::Path::Call(args)] => (*v, &args[..]),
+        _ => return None,
+    };
+    if !globals.enum_variants.get(&typ)?.iter().any(|v| v.name == variant) {
+        return None;
+    }
+    let mut bindings = Vec::new();
+    for arg in args {
+        match &arg.value.values[..] {
+            [ast::Variable {
+                operator: None,
+                value:
+                    ast::ValueLiteral {
+                        body: ast::ValueBody::Symbol(s),
+                    },
+                path,
+                ..
+            }] if arg.symbol.is_none() && arg.value.operators.is_empty() && path.is_empty() => {
+                bindings.push(*s)
+            }
+            // anything else is a normal pattern, like @shape::circle(3)
+            _ => return None,
+        }
+    }
+    Some(VariantPattern {
+        typ,
+        variant,
+        bindings,
+    })
+}
+
+// runs the body of the match case that matched
+fn eval_case_body(
+    body: &ast::CaseBody,
+    full_context: &mut FullContext,
+    globals: &mut Globals,
+    info: CompilerInfo,
+    constant: bool,
+) -> Result<(), RuntimeError> {
+    match body {
+        ast::CaseBody::Expr(expr) => expr.eval(full_context, globals, info, constant)?,
+        ast::CaseBody::Block(stmts) => {
+            compile_scope(stmts, full_context, globals, info)?;
+        }
+    }
+    for c in full_context.iter() {
+        c.inner().broken = Some((
+            BreakType::Switch(c.inner().return_value),
+            CodeArea::new(),
+        ))
+    }
+    Ok(())
+}
+
+// a value in an f-string, shown like $.display shows it
+fn format_value(
+    val: StoredValue,
+    spec: &ast::FormatSpec,
+    context: &mut FullContext,
+    globals: &mut Globals,
+    info: &CompilerInfo,
+) -> Result<String, RuntimeError> {
+    let is_number = matches!(globals.stored_values[val], Value::Number(_));
+    let text = match (&globals.stored_values[val], spec.precision) {
+        (Value::Number(n), Some(precision)) => format!("{:.*}", precision, n),
+        (_, Some(_)) => {
+            return Err(RuntimeError::TypeError {
+                expected: "number (for the precision in the format spec)".to_string(),
+                found: globals.get_type_str(val),
+                val_def: globals.get_area(val),
+                info: info.clone(),
+            })
+        }
+        (Value::Str(s), None) => s.clone(),
+        (_, None) => {
+            handle_unary_operator(val, Builtin::DisplayOp, context, globals, info)?;
+            match &globals.stored_values[context.inner().return_value] {
+                Value::Str(s) => s.clone(),
+                v => v.clone().to_str(globals),
+            }
+        }
+    };
+
+    let len = text.chars().count();
+    let pad = match spec.width {
+        Some(width) if width > len => width - len,
+        _ => return Ok(text),
+    };
+    if spec.zero && spec.align.is_none() {
+        // -0012, not 00-12
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text.as_str()),
+        };
+        return Ok(format!("{}{}{}", sign, "0".repeat(pad), digits));
+    }
+    let default_align = if is_number {
+        ast::FormatAlign::Right
+    } else {
+        ast::FormatAlign::Left
+    };
+    let (left, right) = match spec.align.unwrap_or(default_align) {
+        ast::FormatAlign::Left => (0, pad),
+        ast::FormatAlign::Right => (pad, 0),
+        ast::FormatAlign::Center => (pad / 2, pad - pad / 2),
+    };
+    let fill = spec.fill.unwrap_or(' ').to_string();
+    Ok(format!("{}{}{}", fill.repeat(left), text, fill.repeat(right)))
+}
+
 pub fn macro_to_value(
     m: &ast::Macro,
     contexts: &mut FullContext,
@@ -1684,6 +1941,7 @@ pub fn macro_to_value(
                     .map(|(name, s)| (*name, s.last().unwrap().val))
                     .collect(),
                 def_file: info.position.file,
+                def_module: info.current_module.clone(),
                 arg_pos: m.arg_pos,
                 tag: m.properties.clone(),
                 ret_pattern,
@@ -1743,6 +2001,14 @@ impl VariableFuncs for ast::Variable {
         for full_context in contexts.iter() {
             match &self.value.body {
                 ast::ValueBody::Resolved(r) => full_context.inner().return_value = *r,
+                ast::ValueBody::PatternVar(name) => {
+                    full_context.inner().return_value = store_const_value(
+                        Value::Pattern(Pattern::Var(*name)),
+                        globals,
+                        full_context.inner().start_group,
+                        info.position,
+                    )
+                }
                 ast::ValueBody::SelfVal => {
                     if let Some(val) = full_context.inner().get_variable(globals.SELF_MEMBER_NAME) {
                         full_context.inner().return_value = val
@@ -1919,6 +2185,39 @@ impl VariableFuncs for ast::Variable {
                         }
                     }
                 }
+                ast::ValueBody::Str(ast::StrInner {
+                    flags: Some(ast::StringFlags::Format(parts)),
+                    ..
+                }) => {
+                    let exprs = parts
+                        .iter()
+                        .filter_map(|part| match part {
+                            ast::FormatPart::Value(expr, _) => Some(expr.clone()),
+                            ast::FormatPart::Text(_) => None,
+                        })
+                        .collect();
+                    let combinations =
+                        all_combinations(exprs, full_context, globals, info.clone(), constant)?;
+                    for (values, context) in combinations {
+                        let mut values = values.into_iter();
+                        let mut out = String::new();
+                        for part in parts {
+                            match part {
+                                ast::FormatPart::Text(text) => out += text,
+                                ast::FormatPart::Value(_, spec) => {
+                                    let val = values.next().unwrap();
+                                    out += &format_value(val, spec, context, globals, &info)?;
+                                }
+                            }
+                        }
+                        context.inner().return_value = store_const_value(
+                            Value::Str(out),
+                            globals,
+                            context.inner().start_group,
+                            info.position,
+                        )
+                    }
+                }
                 ast::ValueBody::Str(s) => {
                     full_context.inner().return_value = store_const_value(
                         Value::Str(s.inner.clone()),
@@ -2074,6 +2373,7 @@ impl VariableFuncs for ast::Variable {
 
                                         con_iter.exit_scope();
                                     }
+                                    check_context_limit(context, globals, &info)?;
                                 }
                                 //println!("{:?}", out);
                             }
@@ -2466,6 +2766,7 @@ impl VariableFuncs for ast::Variable {
                         a.clone(),
                         full_context,
                         globals,
+                        &info,
                         |item: &ast::ArrayDef, ctx, list: Vec<StoredValue>, globals| {
                             let mut added = Vec::new();
                             match item.operator {
@@ -2769,12 +3070,118 @@ impl VariableFuncs for ast::Variable {
                 ast::ValueBody::Match(expr, cases) => {
                     expr.eval(full_context, globals, info.clone(), constant)?;
 
+                    let variants = cases
+                        .iter()
+                        .map(|case| match &case.typ {
+                            ast::CaseType::Pattern(p) => variant_pattern(p, globals),
+                            ast::CaseType::Default => None,
+                        })
+                        .collect::<Vec<_>>();
+
+                    // every variant of an enum has to be handled, unless there's an else case
+                    if !cases.iter().any(|c| c.typ == ast::CaseType::Default) {
+                        let mut handled = AHashMap::<TypeId, AHashSet<LocalIntern<String>>>::default();
+                        for v in variants.iter().flatten() {
+                            handled.entry(v.typ).or_default().insert(v.variant);
+                        }
+                        let mut handled = handled.into_iter().collect::<Vec<_>>();
+                        handled.sort_by_key(|(t, _)| *t);
+                        for (typ, names) in handled {
+                            // a case like `@shape:` handles all of them
+                            let whole_type = cases.iter().any(|c| match &c.typ {
+                                ast::CaseType::Pattern(p) => matches!(
+                                    &p.values[..],
+                                    [ast::Variable { value: ast::ValueLiteral { body: ast::ValueBody::TypeIndicator(t) }, path, operator: None, .. }]
+                                        if path.is_empty() && p.operators.is_empty() && globals.type_ids.get(t).map(|t| t.0) == Some(typ)
+                                ),
+                                ast::CaseType::Default => false,
+                            });
+                            let missing = globals.enum_variants[&typ]
+                                .iter()
+                                .filter(|v| !names.contains(&v.name))
+                                .map(|v| v.name.as_str())
+                                .collect::<Vec<_>>();
+                            if !whole_type && !missing.is_empty() {
+                                return Err(RuntimeError::CustomError(create_error(
+                                    info.clone(),
+                                    &format!(
+                                        "this match doesn't handle every variant of @{}",
+                                        find_key_for_value(&globals.type_ids, typ).unwrap()
+                                    ),
+                                    &[(info.position, &format!("Missing: {}", missing.join(", ")))],
+                                    Some("add cases for the missing variants, or an else case"),
+                                )));
+                            }
+                        }
+                    }
+
                     for full_context in full_context.iter() {
                         let val1 = full_context.inner().return_value;
 
-                        for case in cases {
-                            match &case.typ {
-                                ast::CaseType::Pattern(p) => {
+                        for (case, variant) in cases.iter().zip(&variants) {
+                            match (&case.typ, variant) {
+                                (_, Some(variant)) => {
+                                    let fields = globals.enum_variants[&variant.typ]
+                                        .iter()
+                                        .find(|v| v.name == variant.variant)
+                                        .unwrap()
+                                        .fields
+                                        .clone();
+                                    if !variant.bindings.is_empty() && variant.bindings.len() != fields.len() {
+                                        return Err(RuntimeError::CustomError(create_error(
+                                            info.clone(),
+                                            &format!(
+                                                "@{}::{} has {} field(s), but the pattern has {}",
+                                                find_key_for_value(&globals.type_ids, variant.typ).unwrap(),
+                                                variant.variant,
+                                                fields.len(),
+                                                variant.bindings.len(),
+                                            ),
+                                            &[(info.position, "Used in this match")],
+                                            None,
+                                        )));
+                                    }
+
+                                    let matched = match &globals.stored_values[val1] {
+                                        Value::Dict(d) => {
+                                            let is_variant = matches!(
+                                                d.get(&globals.TYPE_MEMBER_NAME).map(|t| &globals.stored_values[*t]),
+                                                Some(Value::TypeIndicator(t)) if *t == variant.typ
+                                            ) && matches!(
+                                                d.get(&globals.VARIANT_MEMBER_NAME).map(|v| &globals.stored_values[*v]),
+                                                Some(Value::Str(s)) if *s == *variant.variant
+                                            );
+                                            if is_variant {
+                                                Some(d.clone())
+                                            } else {
+                                                None
+                                            }
+                                        }
+                                        _ => None,
+                                    };
+
+                                    if let Some(d) = matched {
+                                        // the fields are only defined inside the case
+                                        full_context.enter_scope();
+                                        for (name, field) in variant.bindings.iter().zip(&fields) {
+                                            if name.as_str() == "_" {
+                                                continue;
+                                            }
+                                            let val = d[field];
+                                            full_context.set_variable_and_clone(
+                                                *name,
+                                                val,
+                                                0,
+                                                true,
+                                                globals,
+                                                globals.get_area(val),
+                                            );
+                                        }
+                                        eval_case_body(&case.body, full_context, globals, info.clone(), constant)?;
+                                        full_context.exit_scope();
+                                    }
+                                }
+                                (ast::CaseType::Pattern(p), None) => {
                                     p.eval(full_context, globals, info.clone(), constant)?;
 
                                     for full_context in full_context.iter() {
@@ -2795,36 +3202,7 @@ impl VariableFuncs for ast::Variable {
                                             {
                                                 Value::Bool(b) => {
                                                     if *b {
-                                                        match &case.body {
-                                                            ast::CaseBody::Expr(expr) => expr.eval(
-                                                                full_context,
-                                                                globals,
-                                                                info.clone(),
-                                                                constant,
-                                                            )?,
-                                                            ast::CaseBody::Block(stmts) => {
-                                                                compile_scope(
-                                                                    &stmts,
-                                                                    full_context,
-                                                                    globals,
-                                                                    info.clone(),
-                                                                )?;
-                                                                store_const_value(
-                                                                    Value::Null,
-                                                                    globals,
-                                                                    full_context.inner().start_group,
-                                                                    info.position,
-                                                                );
-                                                            },
-                                                        }
-                                                        for c in full_context.iter() {
-                                                            c.inner().broken = Some((
-                                                                BreakType::Switch(
-                                                                    c.inner().return_value,
-                                                                ),
-                                                                CodeArea::new(),
-                                                            ))
-                                                        }
+                                                        eval_case_body(&case.body, full_context, globals, info.clone(), constant)?;
                                                     }
                                                 }
                                                 a => {
@@ -2844,38 +3222,9 @@ impl VariableFuncs for ast::Variable {
                                     }
                                 }
 
-                                ast::CaseType::Default => {
+                                (ast::CaseType::Default, None) => {
                                     //this should be the last case, so we just return the body
-
-                                    match &case.body {
-                                        ast::CaseBody::Expr(expr) => expr.eval(
-                                            full_context,
-                                            globals,
-                                            info.clone(),
-                                            constant,
-                                        )?,
-                                        ast::CaseBody::Block(stmts) => {
-                                            compile_scope(
-                                                &stmts,
-                                                full_context,
-                                                globals,
-                                                info.clone(),
-                                            )?;
-                                            store_const_value(
-                                                Value::Null,
-                                                globals,
-                                                full_context.inner().start_group,
-                                                info.position,
-                                            );
-                                        },
-                                    }
-
-                                    for c in full_context.iter() {
-                                        c.inner().broken = Some((
-                                            BreakType::Switch(c.inner().return_value),
-                                            CodeArea::new(),
-                                        ))
-                                    }
+                                    eval_case_body(&case.body, full_context, globals, info.clone(), constant)?;
                                 }
                             }
                         }
@@ -3837,7 +4186,7 @@ impl VariableFuncs for ast::Variable {
             }
         }
 
-        merge_all_contexts(contexts, globals, true);
+        merge_all_contexts(contexts, globals, true, info.position);
 
         Ok(())
     }
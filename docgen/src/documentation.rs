@@ -11,6 +11,7 @@ use errors::compiler_info::CompilerInfo;
 
 use compiler::context::FullContext;
 use compiler::globals::Globals;
+use compiler::module_cache::ModuleCache;
 use compiler::{type_id, value::*};
 
 use std::fs::File;
@@ -40,14 +41,17 @@ pub fn document_lib(path: &str) -> Result<(), RuntimeError> {
     let mut globals_path = std::env::current_dir().unwrap();
     globals_path.push("temp"); // this folder doesn't actually exist, but it needs to be there because .parent is called in import_module
     let mut std_out = std::io::stdout();
+    let mut module_cache = ModuleCache::default();
     let mut globals = Globals::new(
         SpwnSource::File(globals_path),
         BuiltinPermissions::new(),
         String::from(""),
         &mut std_out,
+        &mut module_cache,
     );
 
     let mut start_context = FullContext::new(&globals);
+    start_context.set_as_root();
 
     // store_value(Value::Builtins, 1, &mut globals, &start_context);
     // store_value(Value::Null, 1, &mut globals, &start_context);
@@ -90,12 +94,13 @@ pub fn document_lib(path: &str) -> Result<(), RuntimeError> {
             .to_path_buf(),
     );
 
+    let import_type = if is_module {
+        ImportType::Script(PathBuf::from(path))
+    } else {
+        ImportType::Lib(path.to_string())
+    };
     import_module(
-        &if is_module {
-            ImportType::Script(PathBuf::from(path))
-        } else {
-            ImportType::Lib(path.to_string())
-        },
+        &import_type,
         &mut start_context,
         &mut globals,
         info,
@@ -120,7 +125,13 @@ pub fn document_lib(path: &str) -> Result<(), RuntimeError> {
     globals.push_new_preserved();
     globals.push_preserved_val(start_context.inner().return_value);
 
-    let exports = globals.stored_values[start_context.inner().return_value].clone();
+    let mut exports = globals.stored_values[start_context.inner().return_value].clone();
+    // only document what the module exported, not the rest of its definitions
+    if let (Some(names), Value::Dict(dict)) =
+        (globals.module_exports.get(&import_type), &mut exports)
+    {
+        dict.retain(|key, _| names.contains(key));
+    }
     let implementations = globals.implementations.clone();
 
     doc += "_Generated using `spwn doc [file name]`_\n";
@@ -6,6 +6,7 @@ pub struct CompilerInfo {
     pub depth: u8,
     pub call_stack: Vec<CodeArea>,
     pub current_module: String, // empty string means script
+    pub module_chain: Vec<String>, // every library the code was called through, outermost first
     pub position: CodeArea,
 }
 
@@ -16,6 +17,7 @@ impl CompilerInfo {
             call_stack: Vec::new(),
 
             current_module: String::new(),
+            module_chain: Vec::new(),
             position: CodeArea::new(),
         }
     }
@@ -38,6 +40,15 @@ impl CompilerInfo {
         self.call_stack.push(self.position);
         self.position = new;
     }
+
+    // runs the following code as part of `module`, which stays in the chain of libraries
+    // the code was called through
+    pub fn enter_module(&mut self, module: String) {
+        if !module.is_empty() && !self.module_chain.contains(&module) {
+            self.module_chain.push(module.clone());
+        }
+        self.current_module = module;
+    }
 }
 
 impl Default for CompilerInfo {
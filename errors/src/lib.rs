@@ -42,6 +42,12 @@ pub enum RuntimeError {
 
     CustomError(ErrorReport),
 
+    // from a throw statement, with what every context threw
+    ThrowError {
+        messages: Vec<(CodeArea, String)>,
+        info: CompilerInfo,
+    },
+
     BuiltinError {
         builtin: String,
         message: String,
@@ -63,6 +69,15 @@ pub enum RuntimeError {
         context_changes: Vec<CodeArea>,
     },
 
+    ContextLimitError {
+        limit: usize,
+        count: usize,
+        info: CompilerInfo,
+        // where the contexts were split, oldest first
+        splits: Vec<CodeArea>,
+        context_changes: Vec<CodeArea>,
+    },
+
     BreakNeverUsedError {
         breaktype: BreakType,
         info: CompilerInfo,
@@ -72,6 +87,27 @@ pub enum RuntimeError {
     },
 }
 
+impl RuntimeError {
+    // what kind of error this is, for catching it in a try statement
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RuntimeError::UndefinedErr { .. } => "undefined",
+            RuntimeError::PackageSyntaxError { .. } => "package_syntax",
+            RuntimeError::PackageError { .. } => "package",
+            RuntimeError::TypeError { .. } => "type",
+            RuntimeError::PatternMismatchError { .. } => "pattern_mismatch",
+            RuntimeError::CustomError(_) => "custom",
+            RuntimeError::ThrowError { .. } => "throw",
+            RuntimeError::BuiltinError { .. } => "builtin",
+            RuntimeError::MutabilityError { .. } => "mutability",
+            RuntimeError::ContextChangeMutateError { .. } => "context_change_mutate",
+            RuntimeError::ContextChangeError { .. } => "context_change",
+            RuntimeError::ContextLimitError { .. } => "context_limit",
+            RuntimeError::BreakNeverUsedError { .. } => "break_never_used",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct RainbowColorGenerator {
     h: f64,
@@ -95,7 +131,7 @@ impl RainbowColorGenerator {
         let x = c * (1.0 - (h % 2.0 - 1.0).abs());
         let m = hsl.b - c * 0.5;
 
-        let (red, green, blue) = if h >= 0.0 && h < 0.0 {
+        let (red, green, blue) = if (0.0..1.0).contains(&h) {
             (c, x, 0.0)
         } else if (1.0..2.0).contains(&h) {
             (x, c, 0.0)
@@ -312,6 +348,16 @@ impl From<RuntimeError> for ErrorReport {
 
             RuntimeError::CustomError(report) => report,
 
+            RuntimeError::ThrowError { messages, info } => create_error(
+                info,
+                "Runtime Error",
+                &messages
+                    .iter()
+                    .map(|(area, message)| (*area, message.as_str()))
+                    .collect::<Vec<_>>(),
+                None,
+            ),
+
             RuntimeError::BuiltinError {
                 message,
                 info,
@@ -398,6 +444,56 @@ impl From<RuntimeError> for ErrorReport {
                 create_error(info, &message, &labels, None)
             }
 
+            RuntimeError::ContextLimitError {
+                limit,
+                count,
+                info,
+                splits,
+                context_changes,
+            } => {
+                // the same place usually splits the context many times (in a loop for example)
+                let mut split_counts = Vec::<(CodeArea, usize)>::new();
+                for area in splits {
+                    match split_counts.iter_mut().find(|(a, _)| *a == area) {
+                        Some((_, n)) => *n += 1,
+                        None => split_counts.push((area, 1)),
+                    }
+                }
+                let split_labels = split_counts
+                    .iter()
+                    .map(|(area, n)| {
+                        (
+                            *area,
+                            if *n == 1 {
+                                "The context was split here".to_string()
+                            } else {
+                                format!("The context was split here {} times", n)
+                            },
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut labels = split_labels
+                    .iter()
+                    .map(|(a, m)| (*a, m.as_str()))
+                    .collect::<Vec<_>>();
+                for change in context_changes.iter().rev() {
+                    if !split_counts.iter().any(|(a, _)| a == change) {
+                        labels.push((*change, "The trigger function context was changed here"));
+                    }
+                }
+
+                create_error(
+                    info,
+                    &format!(
+                        "Too many contexts ({} at once, the limit is {})",
+                        count, limit
+                    ),
+                    &labels,
+                    Some("Runtime values like counters split the context for every possible outcome. Consider restructuring the code so fewer outcomes are kept apart, or raise the limit with --max-contexts"),
+                )
+            }
+
             RuntimeError::BreakNeverUsedError {
                 info,
                 breaktype,
@@ -4,10 +4,12 @@ use compiler::{
     leveldata::{self, GdObj, ObjParam},
 };
 use ahash::{AHashMap, AHashSet};
+use std::collections::BTreeMap;
 
 mod dead_code;
 mod group_toggling;
 pub mod optimize;
+mod partition;
 mod spawn_optimisation;
 mod trigger_dedup;
 
@@ -61,7 +63,7 @@ pub enum TriggerRole {
     Func,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ReservedIds {
     pub object_groups: AHashSet<Id>,
     pub trigger_groups: AHashSet<Id>, // only includes the obj_props::GROUPS prop
@@ -137,7 +139,8 @@ pub struct ObjPtr(usize, usize);
 //                                     triggers      connections in
 #[derive(Default)]
 pub struct TriggerNetwork {
-    map: AHashMap<Group, TriggerGang>,
+    // ordered, so the passes go through the groups in the same order on every build
+    map: BTreeMap<Group, TriggerGang>,
     connectors: AHashMap<Group, AHashSet<ObjPtr>>,
 }
 
@@ -3,9 +3,9 @@ use compiler::compiler_types::{FunctionId, TriggerOrder};
 use parser::ast::ObjectMode;
 
 use crate::{
-    dead_code, get_role, group_toggling, obj_ids, obj_props, spawn_optimisation, trigger_dedup,
-    ObjPtr, ReservedIds, Swaps, Trigger, TriggerGang, TriggerNetwork, TriggerRole, Triggerlist,
-    NO_GROUP,
+    dead_code, get_role, group_toggling, obj_ids, obj_props, partition, spawn_optimisation,
+    trigger_dedup, ObjPtr, ReservedIds, Swaps, Trigger, TriggerGang, TriggerNetwork, TriggerRole,
+    Triggerlist, NO_GROUP,
 };
 
 //mod icalgebra;
@@ -14,22 +14,135 @@ use compiler::leveldata::{GdObj, ObjParam};
 use ahash::AHashMap;
 
 pub fn optimize(
-    mut obj_in: Vec<FunctionId>,
-    mut closed_group: u16,
-    mut reserved: ReservedIds,
+    obj_in: Vec<FunctionId>,
+    closed_group: u16,
+    reserved: ReservedIds,
 ) -> Vec<FunctionId> {
-    let mut network = TriggerNetwork::default();
+    #[cfg(not(target_arch = "wasm32"))]
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
 
-    let toggle_groups = get_toggle_groups(&obj_in);
+    #[cfg(target_arch = "wasm32")]
+    let threads = 1;
+
+    optimize_with_threads(obj_in, closed_group, reserved, threads)
+}
+
+// optimizes independent parts of the trigger network on separate threads.
+// the parts only depend on the network, and they are put back together in the same
+// order, so the output is the same for any number of threads
+pub fn optimize_with_threads(
+    obj_in: Vec<FunctionId>,
+    closed_group: u16,
+    reserved: ReservedIds,
+    threads: usize,
+) -> Vec<FunctionId> {
+    let partitions = partition::partition_network(&obj_in);
+    let threads = threads.max(1).min(partitions.len());
+
+    let optimized = if threads > 1 {
+        std::thread::scope(|s| {
+            // every thread takes every `threads`th partition
+            let mut queues = vec![Vec::new(); threads];
+            for (i, part) in partitions.into_iter().enumerate() {
+                queues[i % threads].push((i, part));
+            }
+            let handles = queues
+                .into_iter()
+                .map(|queue| {
+                    let reserved = reserved.clone();
+                    s.spawn(move || {
+                        queue
+                            .into_iter()
+                            .map(|(i, part)| (i, optimize_partition(part, reserved.clone())))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut optimized = handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect::<Vec<_>>();
+            optimized.sort_by_key(|(i, _)| *i);
+            optimized.into_iter().map(|(_, part)| part).collect()
+        })
+    } else {
+        partitions
+            .into_iter()
+            .map(|part| optimize_partition(part, reserved.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    // merge the partitions in their original order
+    let mut obj_in = obj_in;
+    for el in &mut obj_in {
+        el.obj_list.clear();
+    }
+    for part in optimized {
+        for (el, part_el) in obj_in.iter_mut().zip(part) {
+            el.obj_list.extend(part_el.obj_list);
+        }
+    }
+
+    // the passes that compare or create groups across the whole network
+    let mut closed_group = closed_group;
+    let mut reserved = reserved;
+    let mut network = build_network(&obj_in);
+    let mut objects = Triggerlist { list: &mut obj_in };
+
+    update_reserved(&mut network, &mut objects, &mut reserved);
+
+    // identical sub-networks can end up in different partitions
+    trigger_dedup::dedup_triggers(&mut network, &mut objects, &reserved);
+
+    clean_network(&mut network, &objects, false);
+
+    group_toggling::group_toggling(&mut network, &mut objects, &reserved, &mut closed_group);
+
+    let zero_group = Group {
+        id: Id::Specific(0),
+    };
+    if let Some(gang) = network.map.get(&zero_group) {
+        if gang.triggers.len() > 1 {
+            let first_obj = gang.triggers[0].obj;
+            closed_group += 1;
+            let new_start_group = Group {
+                id: Id::Arbitrary(closed_group),
+            };
+
+            let mut swaps = Swaps::default();
+            swaps.insert(zero_group, (new_start_group, TriggerOrder(0.0)));
+
+            replace_groups(swaps, &mut objects);
+
+            create_spawn_trigger(
+                Trigger {
+                    obj: first_obj, // arbitrary object
+                    role: TriggerRole::Spawn,
+                    deleted: false,
+                },
+                new_start_group,
+                zero_group,
+                0.0,
+                &mut objects,
+                &mut network,
+                TriggerRole::Spawn,
+                false,
+            );
+        }
+    }
+
+    rebuild(&network, &obj_in)
+}
+
+// sort all triggers by their group
+fn build_network(obj_in: &[FunctionId]) -> TriggerNetwork {
+    let mut network = TriggerNetwork::default();
 
-    // sort all triggers by their group
     for (f, fnid) in obj_in.iter().enumerate() {
         for (o, (obj, _)) in fnid.obj_list.iter().enumerate() {
-            //if let Some(ObjParam::Number(id)) = obj.params.get(&1) {
-            // let mut hd = false;
-            // if let Some(ObjParam::Bool(hd_val)) = obj.params.get(&103) {
-            //     hd = *hd_val;
-            // }
             let trigger = Trigger {
                 obj: ObjPtr(f, o),
                 role: get_role(obj),
@@ -52,10 +165,18 @@ pub fn optimize(
                     }
                 }
             }
-            //}
         }
     }
 
+    network
+}
+
+// runs the optimization passes that only look at the triggers in one partition
+fn optimize_partition(mut obj_in: Vec<FunctionId>, mut reserved: ReservedIds) -> Vec<FunctionId> {
+    let mut network = build_network(&obj_in);
+
+    let toggle_groups = get_toggle_groups(&obj_in);
+
     let mut objects = Triggerlist { list: &mut obj_in };
 
     //optimize
@@ -93,45 +214,6 @@ pub fn optimize(
 
     clean_network(&mut network, &objects, false);
 
-    trigger_dedup::dedup_triggers(&mut network, &mut objects, &reserved);
-
-    clean_network(&mut network, &objects, false);
-
-    group_toggling::group_toggling(&mut network, &mut objects, &reserved, &mut closed_group);
-    //dbg!(&network);
-
-    let zero_group = Group {
-        id: Id::Specific(0),
-    };
-    if let Some(gang) = network.map.get(&zero_group) {
-        if gang.triggers.len() > 1 {
-            closed_group += 1;
-            let new_start_group = Group {
-                id: Id::Arbitrary(closed_group),
-            };
-
-            let mut swaps = Swaps::default();
-            swaps.insert(zero_group, (new_start_group, TriggerOrder(0.0)));
-
-            replace_groups(swaps, &mut objects);
-
-            create_spawn_trigger(
-                Trigger {
-                    obj: ObjPtr(0, 0), // arbitrary object
-                    role: TriggerRole::Spawn,
-                    deleted: false,
-                },
-                new_start_group,
-                zero_group,
-                0.0,
-                &mut objects,
-                &mut network,
-                TriggerRole::Spawn,
-                false,
-            );
-        }
-    }
-
     rebuild(&network, &obj_in)
 }
 
@@ -408,6 +490,7 @@ pub fn create_spawn_trigger(
         func_id: trigger.obj.0,
         mode: ObjectMode::Trigger,
         unique_id: objects[trigger.obj].0.unique_id,
+        output: objects[trigger.obj].0.output,
     };
 
     (*objects.list)[trigger.obj.0]
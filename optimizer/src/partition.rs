@@ -0,0 +1,295 @@
+use compiler::builtins::Group;
+use compiler::compiler_types::FunctionId;
+use compiler::leveldata::ObjParam;
+
+use crate::{ObjPtr, NO_GROUP};
+
+use ahash::AHashMap;
+
+// union-find over the groups used by the triggers
+#[derive(Default)]
+struct GroupSets {
+    parents: AHashMap<Group, Group>,
+}
+
+impl GroupSets {
+    fn find(&mut self, g: Group) -> Group {
+        let mut root = g;
+        loop {
+            let parent = *self.parents.entry(root).or_insert(root);
+            if parent == root {
+                break;
+            }
+            root = parent;
+        }
+        // point everything on the way directly to the root
+        let mut current = g;
+        while current != root {
+            current = self.parents.insert(current, root).unwrap();
+        }
+        root
+    }
+
+    fn union(&mut self, a: Group, b: Group) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parents.insert(b, a);
+        }
+    }
+}
+
+fn referenced_groups(params: &AHashMap<u16, ObjParam>) -> Vec<Group> {
+    let mut out = Vec::new();
+    for param in params.values() {
+        match param {
+            ObjParam::Group(g) => out.push(*g),
+            ObjParam::GroupList(l) => out.extend(l.iter().copied()),
+            _ => (),
+        }
+    }
+    // group 0 is the start group of every top-level trigger, so it doesn't connect anything
+    out.retain(|g| *g != NO_GROUP);
+    out
+}
+
+// partitions are filled with whole components until they have at least this many triggers
+const PARTITION_SIZE: usize = 256;
+
+// splits the triggers into partitions that never reference each other's groups,
+// so that they can be optimized independently.
+// the partitions only depend on how the triggers are connected (not on how many threads
+// there are), and they are in the order their first trigger appears in.
+// every partition has the same function id structure as the input
+pub fn partition_network(obj_in: &[FunctionId]) -> Vec<Vec<FunctionId>> {
+    let mut sets = GroupSets::default();
+
+    for fnid in obj_in {
+        for (obj, _) in &fnid.obj_list {
+            let groups = referenced_groups(&obj.params);
+            for g in groups.iter().skip(1) {
+                sets.union(groups[0], *g);
+            }
+        }
+    }
+
+    // components in the order they first appear
+    let mut component_index = AHashMap::<Group, usize>::default();
+    let mut components = Vec::<Vec<ObjPtr>>::new();
+
+    for (f, fnid) in obj_in.iter().enumerate() {
+        for (o, (obj, _)) in fnid.obj_list.iter().enumerate() {
+            let ptr = ObjPtr(f, o);
+            match referenced_groups(&obj.params).first() {
+                Some(g) => {
+                    let root = sets.find(*g);
+                    let i = *component_index.entry(root).or_insert_with(|| {
+                        components.push(Vec::new());
+                        components.len() - 1
+                    });
+                    components[i].push(ptr);
+                }
+                // doesn't reference any groups, so it's independent of everything
+                None => components.push(vec![ptr]),
+            }
+        }
+    }
+
+    let mut assigned = vec![Vec::<ObjPtr>::new()];
+    for component in components {
+        let last = assigned.last_mut().unwrap();
+        if last.len() >= PARTITION_SIZE {
+            assigned.push(component);
+        } else {
+            last.extend(component);
+        }
+    }
+
+    assigned
+        .into_iter()
+        .filter(|ptrs| !ptrs.is_empty())
+        .map(|mut ptrs| {
+            // keep the original order within each function id
+            ptrs.sort();
+            let mut part = obj_in
+                .iter()
+                .map(|f| FunctionId {
+                    parent: f.parent,
+                    width: f.width,
+                    obj_list: Vec::new(),
+                })
+                .collect::<Vec<_>>();
+            for ObjPtr(f, o) in ptrs {
+                part[f].obj_list.push(obj_in[f].obj_list[o].clone());
+            }
+            part
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimize::optimize_with_threads;
+    use crate::ReservedIds;
+    use compiler::builtins::Id;
+    use compiler::compiler_types::TriggerOrder;
+    use compiler::leveldata::GdObj;
+    use parser::ast::ObjectMode;
+
+    fn group(n: u16) -> ObjParam {
+        ObjParam::Group(Group {
+            id: Id::Arbitrary(n),
+        })
+    }
+
+    fn trigger(func_id: usize, unique_id: usize, params: Vec<(u16, ObjParam)>) -> (GdObj, TriggerOrder) {
+        let obj = GdObj {
+            func_id,
+            params: params.into_iter().collect(),
+            mode: ObjectMode::Trigger,
+            unique_id,
+            output: 0,
+        };
+        (obj, TriggerOrder(unique_id as f64))
+    }
+
+    // a top level spawn trigger for every chain, which spawns a move trigger in a function id of its own
+    fn chains(count: u16) -> (Vec<FunctionId>, Vec<GdObj>, u16) {
+        let mut func_ids = vec![FunctionId {
+            parent: None,
+            width: None,
+            obj_list: Vec::new(),
+        }];
+        let mut objects = Vec::new();
+        for i in 0..count {
+            let (start, target) = (i * 2 + 1, i * 2 + 2);
+            let f = func_ids.len();
+            let id = i as usize * 2;
+            func_ids.push(FunctionId {
+                parent: Some(0),
+                width: None,
+                obj_list: vec![trigger(
+                    f,
+                    id + 1,
+                    vec![
+                        (1, ObjParam::Number(901.0)),
+                        (57, group(start)),
+                        (51, group(target)),
+                        (28, ObjParam::Number(30.0)),
+                    ],
+                )],
+            });
+            func_ids[0].obj_list.push(trigger(
+                0,
+                id,
+                vec![(1, ObjParam::Number(1268.0)), (51, group(start))],
+            ));
+            objects.push(GdObj {
+                func_id: 0,
+                params: vec![(1, ObjParam::Number(1.0)), (57, group(target))]
+                    .into_iter()
+                    .collect(),
+                mode: ObjectMode::Object,
+                unique_id: id,
+                output: 0,
+            });
+        }
+        (func_ids, objects, count * 2)
+    }
+
+    fn groups_in(part: &[FunctionId]) -> Vec<Group> {
+        let mut groups = part
+            .iter()
+            .flat_map(|f| f.obj_list.iter())
+            .flat_map(|(obj, _)| referenced_groups(&obj.params))
+            .collect::<Vec<_>>();
+        groups.sort_by_key(|g| format!("{:?}", g.id));
+        groups.dedup();
+        groups
+    }
+
+    #[test]
+    fn connected_triggers_stay_together() {
+        let (func_ids, _, _) = chains(300);
+        let parts = partition_network(&func_ids);
+
+        // 600 triggers in partitions of at least 256
+        assert_eq!(
+            parts
+                .iter()
+                .map(|p| p.iter().map(|f| f.obj_list.len()).sum::<usize>())
+                .collect::<Vec<_>>(),
+            vec![256, 256, 88]
+        );
+        for part in &parts {
+            assert_eq!(part.len(), func_ids.len());
+            for (f, el) in part.iter().enumerate() {
+                assert_eq!(el.parent, func_ids[f].parent);
+            }
+        }
+        // every chain is in exactly one partition
+        for (i, a) in parts.iter().enumerate() {
+            let a = groups_in(a);
+            for b in &parts[i + 1..] {
+                assert!(groups_in(b).iter().all(|g| !a.contains(g)));
+            }
+        }
+        // in the order they were in
+        assert_eq!(parts[0][0].obj_list[0].0.unique_id, 0);
+        assert_eq!(parts[1][0].obj_list[0].0.unique_id, 256);
+    }
+
+    #[test]
+    fn small_networks_are_one_partition() {
+        let (func_ids, _, _) = chains(10);
+        let parts = partition_network(&func_ids);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0], func_ids);
+    }
+
+    #[test]
+    fn same_output_for_any_thread_count() {
+        let (func_ids, objects, closed_group) = chains(600);
+        let reserved = ReservedIds::from_objects(&objects, &func_ids);
+
+        // the triggers of every function id, in a fixed order
+        let canonical = |func_ids: Vec<FunctionId>| {
+            func_ids
+                .into_iter()
+                .map(|f| {
+                    let mut triggers = f
+                        .obj_list
+                        .iter()
+                        .map(|(obj, order)| {
+                            let mut params = obj
+                                .params
+                                .iter()
+                                .map(|(k, v)| format!("{}:{:?}", k, v))
+                                .collect::<Vec<_>>();
+                            params.sort();
+                            format!("{} {}", order.0, params.join(","))
+                        })
+                        .collect::<Vec<_>>();
+                    triggers.sort();
+                    triggers
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let one = canonical(optimize_with_threads(
+            func_ids.clone(),
+            closed_group,
+            reserved.clone(),
+            1,
+        ));
+        for threads in [2, 3, 8] {
+            let many = canonical(optimize_with_threads(
+                func_ids.clone(),
+                closed_group,
+                reserved.clone(),
+                threads,
+            ));
+            assert_eq!(one, many);
+        }
+    }
+}
@@ -137,7 +137,10 @@ pub(crate) fn spawn_optimisation(
 
     //bg!(&spawn_connections, &all);
 
-    for start in inputs.clone() {
+    // the groups are visited in order, so the same triggers are made on every build
+    let mut sorted_inputs = inputs.iter().copied().collect::<Vec<_>>();
+    sorted_inputs.sort();
+    for start in sorted_inputs {
         let mut visited = Vec::new();
         look_for_cycle(
             start,
@@ -160,7 +163,9 @@ pub(crate) fn spawn_optimisation(
     // go from every trigger in an input group and get every possible path to an
     // output group (stopping if it reaches a group already visited)
 
-    for start in inputs {
+    let mut sorted_inputs = inputs.into_iter().collect::<Vec<_>>();
+    sorted_inputs.sort();
+    for start in sorted_inputs {
         //println!("<{:?}>", start);
         let mut visited = Vec::new();
         traverse(
@@ -208,6 +213,8 @@ pub(crate) fn spawn_optimisation(
     //     end_counts.entry(end).and_modify(|c| *c += 1).or_insert(1);
     // }
 
+    let mut deduped = deduped.into_iter().collect::<Vec<_>>();
+    deduped.sort_by_key(|((start, end, delay), _)| (*start, *end, delay.delay, delay.epsiloned));
     for ((start, end, delay), trigger) in deduped {
         let d = if delay.delay < 50 && delay.epsiloned {
             50
@@ -227,7 +234,9 @@ pub(crate) fn spawn_optimisation(
             )
         };
 
-        let mut insert_to_swaps = |a: Group, b: Group, objects: &mut Triggerlist| {
+        // a group can only be swapped for one other group, so the spawn trigger is kept
+        // when the group was already swapped through another path
+        let insert_to_swaps = |swaps: &mut Swaps, a: Group, b: Group, objects: &mut Triggerlist| {
             let order = objects[trigger.obj].1;
             for v in swaps.values_mut() {
                 if v.0 == a {
@@ -260,16 +269,20 @@ pub(crate) fn spawn_optimisation(
             || toggle_groups.stops.contains_key(&end)
         {
             plain_trigger(network)
-        } else if d == 0 && !is_start_group(end, reserved) && network.map[&end].connections_in == 1
+        } else if d == 0
+            && !is_start_group(end, reserved)
+            && network.map[&end].connections_in == 1
+            && !swaps.contains_key(&end)
         {
             //dbg!(end, start);
-            insert_to_swaps(end, start, objects);
+            insert_to_swaps(&mut swaps, end, start, objects);
         } else if d == 0 && !is_start_group(start, reserved)
                 && network.map[&start].connections_in == 1 //??
                 && (network.map[&start].triggers.is_empty()
                     || network.map[&start].triggers.iter().all(|t| t.deleted))
+                && !swaps.contains_key(&start)
         {
-            insert_to_swaps(start, end, objects);
+            insert_to_swaps(&mut swaps, start, end, objects);
         } else {
             plain_trigger(network)
         }
@@ -46,7 +46,23 @@ pub enum StatementBody {
     Expr(Expression),
     Definition(Definition),
 
-    TypeDef { name: String, attr: Attribute },
+    TypeDef {
+        name: String,
+        attr: Attribute,
+        // empty for types that aren't enums
+        variants: Vec<EnumVariant>,
+    },
+    // interface @name { member, ... }
+    Interface {
+        name: String,
+        attr: Attribute,
+        members: Vec<LocalIntern<String>>,
+    },
+
+    // sync a, a group of parts that never run at the same time
+    SyncGroup(LocalIntern<String>),
+    // a[part] { ... }
+    SyncPart(SyncPart),
 
     Return(Option<Expression>),
     Impl(Implementation),
@@ -54,6 +70,7 @@ pub enum StatementBody {
     For(For),
     While(While),
     Error(Error),
+    TryCatch(TryCatch),
     Extract(Expression),
 
     Break,
@@ -63,11 +80,20 @@ pub enum StatementBody {
 
 // TODO: implement this in parser and compiler
 
+#[derive(Clone, PartialEq, Debug)]
+pub struct SyncPart {
+    pub group: LocalIntern<String>,
+    pub part: Expression,
+    pub body: Vec<Statement>,
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Definition {
     pub symbol: Variable,
     pub value: Option<Expression>,
     pub mutable: bool,
+    // `export name = value` at the top of a module
+    pub exported: bool,
 }
 #[derive(Clone, PartialEq, Debug)]
 pub struct ValueLiteral {
@@ -103,6 +129,8 @@ pub enum ValueBody {
     Resolved(StoredValue),
     TypeIndicator(String),
     MacroPattern(MacroPattern),
+    // ?T, a pattern that binds to the type of the first value it matches
+    PatternVar(LocalIntern<String>),
     SelfVal,
     Ternary(Ternary),
     Null,
@@ -150,6 +178,33 @@ pub enum StringFlags {
     Base64,
     Raw,
     Unindent,
+    // f"...", the string is made from these when it's evaluated
+    Format(Vec<FormatPart>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum FormatPart {
+    Text(String),
+    Value(Expression, FormatSpec),
+}
+
+// what comes after the : in f"{value:spec}", like in rust: [[fill]align][0][width][.precision]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct FormatSpec {
+    pub fill: Option<char>,
+    pub align: Option<FormatAlign>,
+    // pad numbers with zeros after the sign
+    pub zero: bool,
+    pub width: Option<usize>,
+    // digits after the decimal point, only for numbers
+    pub precision: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FormatAlign {
+    Left,
+    Right,
+    Center,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -392,6 +447,14 @@ pub struct For {
     pub body: Vec<Statement>,
 }
 
+#[derive(Clone, PartialEq, Debug)]
+pub struct TryCatch {
+    pub try_body: Vec<Statement>,
+    // where the error is stored, if anywhere
+    pub catch_symbol: Option<Expression>,
+    pub catch_body: Vec<Statement>,
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct While {
     pub condition: Expression,
@@ -498,9 +561,19 @@ pub struct CompoundStatement {
     pub statements: Vec<Statement>,
 }
 
+// a variant of an enum type, like `circle(radius)` in `type @shape { circle(radius), empty }`
+#[derive(Clone, PartialEq, Debug)]
+pub struct EnumVariant {
+    pub name: LocalIntern<String>,
+    pub fields: Vec<LocalIntern<String>>,
+    pub pos: FileRange,
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Implementation {
     pub symbol: Variable,
+    // impl @type: @interface { ... }
+    pub interface: Option<Variable>,
     pub members: Vec<DictDef>,
 }
 
@@ -223,7 +223,31 @@ impl SpwnFmt for StatementBody {
             //StatementBody::Definition(def) => format!("{}", def.fmt(ind)),
             StatementBody::Call(call) => call.fmt(ind),
             StatementBody::Expr(x) => x.fmt(ind),
-            StatementBody::TypeDef { name, .. } => format!("type {}", name),
+            StatementBody::TypeDef { name, variants, .. } => {
+                if variants.is_empty() {
+                    format!("type {}", name)
+                } else {
+                    let variants = variants
+                        .iter()
+                        .map(|v| {
+                            if v.fields.is_empty() {
+                                v.name.to_string()
+                            } else {
+                                let fields: Vec<&str> =
+                                    v.fields.iter().map(|f| f.as_str()).collect();
+                                format!("{}({})", v.name, fields.join(", "))
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    format!("type {} {{ {} }}", name, variants.join(", "))
+                }
+            }
+            StatementBody::Interface { name, members, .. } => {
+                let members: Vec<&str> = members.iter().map(|m| m.as_str()).collect();
+                format!("interface {} {{ {} }}", name, members.join(", "))
+            }
+            StatementBody::SyncGroup(name) => format!("sync {}", name),
+            StatementBody::SyncPart(x) => x.fmt(ind),
             StatementBody::Return(x) => match x {
                 Some(expr) => format!("return {}", expr.fmt(ind)),
                 None => "return".to_string(),
@@ -234,6 +258,7 @@ impl SpwnFmt for StatementBody {
             StatementBody::For(x) => x.fmt(ind),
             StatementBody::While(_) => "While loop lol".to_string(),
             StatementBody::Error(x) => x.fmt(ind),
+            StatementBody::TryCatch(x) => x.fmt(ind),
             StatementBody::Extract(x) => format!("extract {}", x.fmt(ind)),
             StatementBody::Break => String::from("break"),
             StatementBody::Continue => String::from("continue"),
@@ -270,7 +295,10 @@ impl SpwnFmt for ValueBody {
             Symbol(x) => x.to_string(),
             Bool(x) => format!("{}", x),
             Expression(x) => format!("({})", x.fmt(ind)),
-            Str(x) => format!("\"{}\"", x.inner),
+            Str(x) => match x.flags {
+                Some(StringFlags::Format(_)) => format!("f\"{}\"", x.inner),
+                _ => format!("\"{}\"", x.inner),
+            },
             Import(x, f) => format!("import{} {:?}", if *f { "!" } else { "" }, x),
             Obj(x) => {
                 (match x.mode {
@@ -281,6 +309,7 @@ impl SpwnFmt for ValueBody {
             Macro(x) => x.fmt(ind),
             Resolved(_) => "<val>".to_string(),
             TypeIndicator(x) => format!("@{}", x),
+            PatternVar(x) => format!("?{}", x),
             Null => "null".to_string(),
             SelfVal => "self".to_string(),
             Ternary(t) => {
@@ -376,6 +405,43 @@ impl SpwnFmt for For {
     }
 }
 
+impl SpwnFmt for SyncPart {
+    fn fmt(&self, ind: Indent) -> String {
+        format!(
+            "{}[{}] {{\n{}\n{}}}",
+            self.group,
+            self.part.fmt(ind),
+            CompoundStatement {
+                statements: self.body.clone()
+            }
+            .fmt(ind + 4),
+            tabs(ind)
+        )
+    }
+}
+
+impl SpwnFmt for TryCatch {
+    fn fmt(&self, ind: Indent) -> String {
+        format!(
+            "try {{\n{}\n{}}} catch {}{{\n{}\n{}}}",
+            CompoundStatement {
+                statements: self.try_body.clone()
+            }
+            .fmt(ind + 4),
+            tabs(ind),
+            match &self.catch_symbol {
+                Some(symbol) => format!("{} ", symbol.fmt(ind)),
+                None => String::new(),
+            },
+            CompoundStatement {
+                statements: self.catch_body.clone()
+            }
+            .fmt(ind + 4),
+            tabs(ind)
+        )
+    }
+}
+
 impl SpwnFmt for Variable {
     fn fmt(&self, ind: Indent) -> String {
         let mut out = String::new();
@@ -502,7 +568,8 @@ impl SpwnFmt for UnaryOperator {
 impl SpwnFmt for Definition {
     fn fmt(&self, ind: Indent) -> String {
         format!(
-            "{}{}{}",
+            "{}{}{}{}",
+            if self.exported { "export " } else { "" },
             if self.mutable { "let " } else { "" },
             self.symbol.fmt(ind),
             if let Some(value) = &self.value {
@@ -545,7 +612,12 @@ impl SpwnFmt for CompoundStatement {
 
 impl SpwnFmt for Implementation {
     fn fmt(&self, ind: Indent) -> String {
-        format!("impl {} ", self.symbol.fmt(ind)) + &element_list(&self.members, '{', '}', ind)
+        let interface = match &self.interface {
+            Some(i) => format!(": {}", i.fmt(ind)),
+            None => String::new(),
+        };
+        format!("impl {}{} ", self.symbol.fmt(ind), interface)
+            + &element_list(&self.members, '{', '}', ind)
     }
 }
 
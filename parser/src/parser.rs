@@ -187,7 +187,7 @@ pub enum Token {
     #[token("false")]
     False,
 
-    #[regex(r"([0-9]+|\?)[gbci]")]
+    #[regex(r"[0-9]+[gbci]")]
     Id,
 
     //TERMINATORS
@@ -230,6 +230,10 @@ pub enum Token {
     #[token("@")]
     At,
 
+    // ?g, ?c, ?b and ?i are lexed here as well, and parsed as arbitrary IDs
+    #[regex(r"\?[a-zA-Z_][a-zA-Z0-9_]*")]
+    PatternVar,
+
     #[token("#")]
     Hash,
 
@@ -254,6 +258,15 @@ pub enum Token {
     #[token("throw")]
     ErrorStatement,
 
+    #[token("try")]
+    Try,
+
+    #[token("export")]
+    Export,
+
+    #[token("catch")]
+    Catch,
+
     #[token("if")]
     If,
 
@@ -295,6 +308,9 @@ pub enum Token {
     #[token("type")]
     Type,
 
+    #[token("interface")]
+    Interface,
+
     #[token("let")]
     Let,
 
@@ -324,6 +340,7 @@ impl Token {
                 "operator"
             }
             Symbol => "identifier",
+            PatternVar => "pattern variable",
             Number | BinaryLiteral | HexLiteral | OctalLiteral => "number literal",
             StringLiteral => "string literal",
             True | False => "boolean literal",
@@ -333,11 +350,13 @@ impl Token {
             | ClosingSquareBracket | OpenBracket | ClosingBracket | Colon | DoubleColon
             | Period | DotDot | DotDotEq | At | Hash | Arrow | ThickArrow => "terminator",
 
-            Sync => "reserved keyword (not currently in use, but may be used in future updates)",
             Switch => "Deprecated keyword, use `match` instead",
 
-            Return | Implement | For | ErrorStatement | If | Else | Object | Trigger | Import
-            | Extract | Null | Type | Let | SelfVal | Break | Continue | Match | While => "keyword",
+            Return | Implement | For | ErrorStatement | Try | Catch | Export | If | Else | Object
+            | Trigger | Import | Extract | Null | Type | Interface | Let | SelfVal | Break
+            | Continue | Match | While | Sync => {
+                "keyword"
+            }
             //Comment | MultiCommentStart | MultiCommentEnd => "comment",
             StatementSeparator => "statement separator",
             Error => "unknown",
@@ -368,6 +387,8 @@ pub struct Tokens<'a> {
     line_breaks: Vec<u32>,
     //index 0 = element of iter / last element in stack
     index: TokenPos,
+    // where the lexed text starts in the file, when it's only a part of it
+    offset: usize,
 }
 
 impl<'a> Tokens<'a> {
@@ -377,6 +398,7 @@ impl<'a> Tokens<'a> {
             stack: Vec::new(),
             line_breaks: vec![0],
             index: 0,
+            offset: 0,
         }
     }
 
@@ -385,7 +407,8 @@ impl<'a> Tokens<'a> {
             let next_elem = self.iter.next();
 
             let slice = self.iter.slice().to_string();
-            let range = self.iter.span();
+            let span = self.iter.span();
+            let range = span.start + self.offset..span.end + self.offset;
 
             self.stack.push((next_elem, slice, range));
             next_elem
@@ -629,6 +652,16 @@ fn parse_cmp_stmt(
                 tokens.previous_no_ignore(false);
 
                 let parsed = parse_statement(tokens, notes)?;
+                if let ast::StatementBody::Definition(ast::Definition { exported: true, .. }) =
+                    parsed.body
+                {
+                    return Err(SyntaxError::SyntaxError {
+                        message: "'export' can only be used at the top level of a file"
+                            .to_string(),
+                        pos: parsed.pos,
+                        file: notes.file.clone(),
+                    });
+                }
                 // if parsed.comment.0 == None && !statements.is_empty() {
                 //     parsed.comment.0 = statements.last().unwrap().comment.1.clone();
                 //     (*statements.last_mut().unwrap()).comment.1 = None;
@@ -707,6 +740,28 @@ pub fn parse_statement(
             */
         }
 
+        Some(Token::Export) => {
+            // export name = value, the module returns a dictionary of everything it exports
+            let rest_of_statement = parse_statement(tokens, notes)?;
+            match rest_of_statement.body {
+                ast::StatementBody::Definition(mut def)
+                    if def.symbol.path.is_empty()
+                        && def.symbol.operator.is_none()
+                        && matches!(def.symbol.value.body, ast::ValueBody::Symbol(_)) =>
+                {
+                    def.exported = true;
+                    ast::StatementBody::Definition(def)
+                }
+                _ => {
+                    return Err(SyntaxError::SyntaxError {
+                        message: "only definitions of a name can be exported".to_string(),
+                        pos: (start_pos, rest_of_statement.pos.1),
+                        file: notes.file.clone(),
+                    })
+                }
+            }
+        }
+
         Some(Token::Let) => {
             // definition statement (at last)
             // this branch only handles the immutable case, the immutable case is handled in the expression branch,
@@ -723,6 +778,7 @@ pub fn parse_statement(
                 symbol,
                 value,
                 mutable: true,
+                exported: false,
             })
         }
 
@@ -887,6 +943,39 @@ pub fn parse_statement(
             //i dont think a summary is needed for this
         }
 
+        Some(Token::Try) => {
+            match tokens.next(false) {
+                Some(Token::OpenCurlyBracket) => (),
+                a => expected!("'{'".to_string(), tokens, notes, a),
+            }
+            let try_body = parse_cmp_stmt(tokens, notes)?;
+
+            match tokens.next(false) {
+                Some(Token::Catch) => (),
+                a => expected!("keyword 'catch'".to_string(), tokens, notes, a),
+            }
+            // the variable for the error can be left out
+            let catch_symbol = match tokens.next(false) {
+                Some(Token::OpenCurlyBracket) => None,
+                _ => {
+                    tokens.previous();
+                    let symbol = parse_variable(tokens, notes, true, None)?.to_expression();
+                    match tokens.next(false) {
+                        Some(Token::OpenCurlyBracket) => (),
+                        a => expected!("'{'".to_string(), tokens, notes, a),
+                    }
+                    Some(symbol)
+                }
+            };
+            let catch_body = parse_cmp_stmt(tokens, notes)?;
+
+            ast::StatementBody::TryCatch(ast::TryCatch {
+                try_body,
+                catch_symbol,
+                catch_body,
+            })
+        }
+
         Some(Token::Type) => {
             // defining a new type
             match tokens.next(false) {
@@ -895,13 +984,24 @@ pub fn parse_statement(
                 a => expected!("'@'".to_string(), tokens, notes, a),
             };
 
-            match tokens.next(false) {
+            let name = match tokens.next(false) {
                 // check if type name is valid
-                Some(Token::Symbol | Token::Trigger) => ast::StatementBody::TypeDef {
-                    name: tokens.slice(),
-                    attr,
-                },
+                Some(Token::Symbol | Token::Trigger) => tokens.slice(),
                 a => expected!("type name".to_string(), tokens, notes, a),
+            };
+
+            // enums list their variants after the name
+            let mut variants = Vec::new();
+            let mut test_tokens = tokens.clone();
+            if test_tokens.next(true) == Some(Token::OpenCurlyBracket) {
+                tokens.next(true);
+                variants = parse_enum_variants(tokens, notes)?;
+            }
+
+            ast::StatementBody::TypeDef {
+                name,
+                attr,
+                variants,
             }
             /*Summary:
             check for @ symbol at the start
@@ -910,6 +1010,52 @@ pub fn parse_statement(
             */
         }
 
+        Some(Token::Interface) => {
+            // the members a type needs to implement to match the interface
+            match tokens.next(false) {
+                Some(Token::At) => (),
+                a => expected!("'@'".to_string(), tokens, notes, a),
+            };
+            let name = match tokens.next(false) {
+                Some(Token::Symbol | Token::Trigger) => tokens.slice(),
+                a => expected!("interface name".to_string(), tokens, notes, a),
+            };
+            match tokens.next(false) {
+                Some(Token::OpenCurlyBracket) => (),
+                a => expected!("'{'".to_string(), tokens, notes, a),
+            };
+
+            let mut members = Vec::new();
+            loop {
+                match tokens.next(false) {
+                    Some(Token::ClosingCurlyBracket) => break,
+                    Some(Token::Symbol) => {
+                        let member = LocalIntern::new(tokens.slice());
+                        if members.contains(&member) {
+                            return Err(SyntaxError::SyntaxError {
+                                message: format!("the member '{}' is listed twice", member),
+                                pos: tokens.position(),
+                                file: notes.file.clone(),
+                            });
+                        }
+                        members.push(member);
+                    }
+                    a => expected!("member name or '}'".to_string(), tokens, notes, a),
+                }
+                match tokens.next(false) {
+                    Some(Token::Comma) => (),
+                    Some(Token::ClosingCurlyBracket) => break,
+                    a => expected!("',' or '}'".to_string(), tokens, notes, a),
+                }
+            }
+
+            ast::StatementBody::Interface {
+                name,
+                attr,
+                members,
+            }
+        }
+
         Some(Token::Implement) => {
             //parse impl statement
             let symbol = parse_variable(tokens, notes, true, None)?;
@@ -922,10 +1068,19 @@ pub fn parse_statement(
                 want to use a variable instead.
             */
 
+            let interface = match tokens.next(false) {
+                Some(Token::Colon) => Some(parse_variable(tokens, notes, true, None)?),
+                _ => {
+                    tokens.previous();
+                    None
+                }
+            };
+
             match tokens.next(false) {
                 // check if it has the brace
                 Some(Token::OpenCurlyBracket) => ast::StatementBody::Impl(ast::Implementation {
                     symbol,
+                    interface,
                     members: parse_dict(tokens, notes)?, // impl block is basically a dict
                 }),
 
@@ -937,6 +1092,11 @@ pub fn parse_statement(
             // honestly this shouldn't deserve a summary its so basic
         }
 
+        Some(Token::Sync) => match tokens.next(false) {
+            Some(Token::Symbol) => ast::StatementBody::SyncGroup(LocalIntern::new(tokens.slice())),
+            a => expected!("sync group name".to_string(), tokens, notes, a),
+        },
+
         Some(Token::Extract) => {
             let expr = parse_expr(tokens, notes, true, true, None)?;
             // its an expression because dicts can also be extracted alongside imported modules
@@ -952,7 +1112,32 @@ pub fn parse_statement(
             //expression or call
             tokens.previous_no_ignore(false);
             let mut expr = parse_expr(tokens, notes, true, true, Some(attr))?;
-            if tokens.next(false) == Some(Token::Exclamation) {
+            let sync_part = match &expr.values[..] {
+                [ast::Variable {
+                    value:
+                        ast::ValueLiteral {
+                            body: ast::ValueBody::Symbol(group),
+                        },
+                    path,
+                    operator: None,
+                    ..
+                }] if expr.operators.is_empty() => match &path[..] {
+                    [ast::Path::Index(part)] => Some((*group, part.clone())),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let (Some((group, part)), Some(Token::OpenCurlyBracket)) =
+                (sync_part, tokens.clone().next(true))
+            {
+                // a[part] { ... }
+                tokens.next(true);
+                ast::StatementBody::SyncPart(ast::SyncPart {
+                    group,
+                    part,
+                    body: parse_cmp_stmt(tokens, notes)?,
+                })
+            } else if tokens.next(false) == Some(Token::Exclamation) {
                 //call
                 ast::StatementBody::Call(ast::Call {
                     function: expr.values.remove(0),
@@ -969,6 +1154,7 @@ pub fn parse_statement(
                         symbol,
                         value: Some(expr),
                         mutable: false,
+                        exported: false,
                     })
                 } else {
                     ast::StatementBody::Expr(expr)
@@ -1127,6 +1313,71 @@ fn fix_precedence(mut expr: ast::Expression) -> ast::Expression {
     }
 }
 
+fn parse_enum_variants(
+    tokens: &mut Tokens,
+    notes: &mut ParseNotes,
+) -> Result<Vec<ast::EnumVariant>, SyntaxError> {
+    let mut variants = Vec::<ast::EnumVariant>::new();
+    loop {
+        match tokens.next(false) {
+            Some(Token::ClosingCurlyBracket) => break,
+            Some(Token::Symbol) => {
+                let start = tokens.position().0;
+                let name = LocalIntern::new(tokens.slice());
+                if variants.iter().any(|v| v.name == name) {
+                    return Err(SyntaxError::SyntaxError {
+                        message: format!("the variant '{}' is defined twice", name),
+                        pos: tokens.position(),
+                        file: notes.file.clone(),
+                    });
+                }
+
+                let mut fields = Vec::new();
+                let mut test_tokens = tokens.clone();
+                if test_tokens.next(false) == Some(Token::OpenBracket) {
+                    tokens.next(false);
+                    loop {
+                        match tokens.next(false) {
+                            Some(Token::ClosingBracket) => break,
+                            Some(Token::Symbol) => {
+                                let field = LocalIntern::new(tokens.slice());
+                                if fields.contains(&field) {
+                                    return Err(SyntaxError::SyntaxError {
+                                        message: format!("the field '{}' is defined twice", field),
+                                        pos: tokens.position(),
+                                        file: notes.file.clone(),
+                                    });
+                                }
+                                fields.push(field);
+                            }
+                            a => expected!("field name or ')'".to_string(), tokens, notes, a),
+                        }
+                        match tokens.next(false) {
+                            Some(Token::Comma) => (),
+                            Some(Token::ClosingBracket) => break,
+                            a => expected!("',' or ')'".to_string(), tokens, notes, a),
+                        }
+                    }
+                }
+
+                variants.push(ast::EnumVariant {
+                    name,
+                    fields,
+                    pos: (start, tokens.position().1),
+                });
+
+                match tokens.next(false) {
+                    Some(Token::Comma) => (),
+                    Some(Token::ClosingCurlyBracket) => break,
+                    a => expected!("',' or '}'".to_string(), tokens, notes, a),
+                }
+            }
+            a => expected!("variant name or '}'".to_string(), tokens, notes, a),
+        }
+    }
+    Ok(variants)
+}
+
 fn parse_cases(tokens: &mut Tokens, notes: &mut ParseNotes) -> Result<Vec<ast::Case>, SyntaxError> {
     let mut default_enabled = false;
 
@@ -1716,11 +1967,13 @@ fn parse_args(
     Ok(args)
 }
 
+// also returns the definitions for the destructured arguments, which go at the start of the body
 fn parse_arg_def(
     tokens: &mut Tokens,
     notes: &mut ParseNotes,
-) -> Result<Vec<ast::ArgDef>, SyntaxError> {
+) -> Result<(Vec<ast::ArgDef>, Vec<ast::Statement>), SyntaxError> {
     let mut args = Vec::<ast::ArgDef>::new();
+    let mut destructures = Vec::<ast::Statement>::new();
     let opening_bracket = tokens.position();
     loop {
         let properties = check_for_tag(tokens, notes)?;
@@ -1740,9 +1993,35 @@ fn parse_arg_def(
             _ => ArgType::Const,
         };
 
-        let symbol = LocalIntern::new(tokens.slice());
+        let mut symbol = LocalIntern::new(tokens.slice());
         let start = tokens.position().0;
 
+        if let Some(Token::OpenSquareBracket | Token::OpenCurlyBracket) = arg_tok {
+            // ([a, b], {x, y: z}) { ... }
+            if argtype == ArgType::Ref {
+                return Err(SyntaxError::SyntaxError {
+                    message: "reference arguments can't be destructured".to_string(),
+                    pos: tokens.position(),
+                    file: notes.file.clone(),
+                });
+            }
+            tokens.previous();
+            let pattern = parse_expr(tokens, notes, false, false, None)?;
+            let pos = (start, tokens.position().1);
+            // the argument gets a hidden name, which can't be written as a symbol
+            symbol = LocalIntern::new(format!("#{}", args.len()));
+            destructures.push(ast::Statement {
+                body: ast::StatementBody::Definition(ast::Definition {
+                    symbol: pattern.to_variable(),
+                    value: Some(ast::ValueBody::Symbol(symbol).to_variable(pos).to_expression()),
+                    mutable: argtype == ArgType::Mut,
+                    exported: false,
+                }),
+                arrow: false,
+                pos,
+            });
+        }
+
         args.push(match tokens.next(false) {
             Some(Token::Assign) => {
                 if arg_tok == Some(Token::SelfVal) {
@@ -1805,7 +2084,12 @@ fn parse_arg_def(
                 }
 
                 match arg_tok {
-                    Some(Token::Symbol) | Some(Token::SelfVal) => (
+                    Some(
+                        Token::Symbol
+                        | Token::SelfVal
+                        | Token::OpenSquareBracket
+                        | Token::OpenCurlyBracket,
+                    ) => (
                         symbol,
                         None,
                         properties,
@@ -1849,7 +2133,7 @@ fn parse_arg_def(
     }
     //tokens.previous();
 
-    Ok(args)
+    Ok((args, destructures))
 }
 
 fn check_for_tag(
@@ -2041,6 +2325,13 @@ pub fn str_content(
             out.1 = StringFlags::Raw.into();
             out.0 = chars.collect()
         }
+        "f" => {
+            // where the content starts in the file, after the flag and the quote
+            let start = tokens.position().0 + string_flag.len() + 1;
+            let parts = format_parts(chars.as_str(), start, tokens, notes)?;
+            out.0 = chars.collect();
+            out.1 = StringFlags::Format(parts).into();
+        }
         "u" => {
             out.1 = StringFlags::Unindent.into();
 
@@ -2103,6 +2394,187 @@ pub fn str_content(
     Ok(out)
 }
 
+// splits the content of an f-string into text and {expression:spec} parts
+fn format_parts(
+    raw: &str,
+    start: usize,
+    tokens: &Tokens,
+    notes: &mut ParseNotes,
+) -> Result<Vec<ast::FormatPart>, SyntaxError> {
+    let error = |message: &str, pos: FileRange, notes: &ParseNotes| SyntaxError::SyntaxError {
+        message: message.to_string(),
+        pos,
+        file: notes.file.clone(),
+    };
+
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        let offset = start + raw.len() - chars.as_str().len() - c.len_utf8();
+        match c {
+            '\\' => text.push(char_escape(&mut chars, tokens, notes)?),
+            '{' | '}' if chars.as_str().starts_with(c) => {
+                chars.next();
+                text.push(c);
+            }
+            '}' => {
+                return Err(error(
+                    "Unmatched '}' in format string (write '}}' for a '}')",
+                    (offset, offset + 1),
+                    notes,
+                ))
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let rest_start = offset + 1;
+
+                // the closing bracket, and the ':' before the spec if there is one
+                let mut depth = 0;
+                let mut quote = None;
+                let mut end = None;
+                let mut colon = None;
+                let mut inner = rest.char_indices().peekable();
+                while let Some((i, c)) = inner.next() {
+                    match (quote, c) {
+                        (Some(_), '\\') => {
+                            inner.next();
+                        }
+                        (Some(q), c) if c == q => quote = None,
+                        (Some(_), _) => (),
+                        (None, '"' | '\'') => quote = Some(c),
+                        (None, '(' | '[' | '{') => depth += 1,
+                        (None, '}') if depth == 0 => {
+                            end = Some(i);
+                            break;
+                        }
+                        (None, ')' | ']' | '}') => depth -= 1,
+                        // :: is an operator
+                        (None, ':') if depth == 0 && colon.is_none() => {
+                            if let Some((_, ':')) = inner.peek() {
+                                inner.next();
+                            } else {
+                                colon = Some(i);
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                let end = match end {
+                    Some(end) => end,
+                    None => {
+                        return Err(error(
+                            "Unclosed '{' in format string (write '{{' for a '{')",
+                            (offset, offset + 1),
+                            notes,
+                        ))
+                    }
+                };
+                let expr_text = &rest[..colon.unwrap_or(end)];
+                if expr_text.trim().is_empty() {
+                    return Err(error(
+                        "Expected an expression between the brackets",
+                        (offset, rest_start + end + 1),
+                        notes,
+                    ));
+                }
+
+                // the expression is lexed on its own, but keeps its positions in the file
+                let mut expr_tokens = Tokens::new(Token::lexer(expr_text));
+                expr_tokens.offset = rest_start;
+                expr_tokens.line_breaks = tokens.line_breaks.clone();
+                let expr = parse_expr(&mut expr_tokens, notes, true, true, None)?;
+                if let a @ Some(_) = expr_tokens.next(false) {
+                    expected!("'}' or ':'".to_string(), expr_tokens, notes, a)
+                }
+
+                let spec = match colon {
+                    Some(colon) => format_spec(
+                        &rest[colon + 1..end],
+                        (rest_start + colon + 1, rest_start + end),
+                        notes,
+                    )?,
+                    None => ast::FormatSpec::default(),
+                };
+
+                if !text.is_empty() {
+                    parts.push(ast::FormatPart::Text(std::mem::take(&mut text)));
+                }
+                parts.push(ast::FormatPart::Value(expr, spec));
+                chars = rest[end + 1..].chars();
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(ast::FormatPart::Text(text));
+    }
+    Ok(parts)
+}
+
+// [[fill]align][0][width][.precision]
+fn format_spec(
+    spec: &str,
+    pos: FileRange,
+    notes: &ParseNotes,
+) -> Result<ast::FormatSpec, SyntaxError> {
+    let invalid = || SyntaxError::SyntaxError {
+        message: format!(
+            "Invalid format spec `{}` (expected something like `>8`, `.2` or `08.3`)",
+            spec
+        ),
+        pos,
+        file: notes.file.clone(),
+    };
+    let align = |c: char| match c {
+        '<' => Some(ast::FormatAlign::Left),
+        '>' => Some(ast::FormatAlign::Right),
+        '^' => Some(ast::FormatAlign::Center),
+        _ => None,
+    };
+    // the number at the start of `rest`, if there is one
+    let number = |rest: &mut &str| -> Result<Option<usize>, SyntaxError> {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return Ok(None);
+        }
+        let n = rest[..digits].parse().map_err(|_| invalid())?;
+        *rest = &rest[digits..];
+        Ok(Some(n))
+    };
+
+    let mut out = ast::FormatSpec::default();
+    let mut rest = spec;
+
+    let mut first_chars = rest.chars();
+    match (first_chars.next(), first_chars.next()) {
+        (Some(fill), Some(a)) if align(a).is_some() => {
+            out.fill = Some(fill);
+            out.align = align(a);
+            rest = &rest[fill.len_utf8() + 1..];
+        }
+        (Some(a), _) if align(a).is_some() => {
+            out.align = align(a);
+            rest = &rest[1..];
+        }
+        _ => (),
+    }
+    if let Some(r) = rest.strip_prefix('0') {
+        out.zero = true;
+        rest = r;
+    }
+    out.width = number(&mut rest)?;
+    if let Some(r) = rest.strip_prefix('.') {
+        rest = r;
+        out.precision = Some(number(&mut rest)?.ok_or_else(invalid)?);
+    }
+    if !rest.is_empty() {
+        return Err(invalid());
+    }
+    Ok(out)
+}
+
 fn check_if_slice(mut tokens: Tokens, notes: &mut ParseNotes) -> Result<bool, SyntaxError> {
     loop {
         match tokens.next(false) {
@@ -2166,7 +2638,7 @@ fn try_parse_macro(
     let parse_macro_def =
         |tokens: &mut Tokens, notes: &mut ParseNotes| -> Result<ast::ValueBody, SyntaxError> {
             let arg_start = tokens.position().0;
-            let args = parse_arg_def(tokens, notes)?;
+            let (args, destructures) = parse_arg_def(tokens, notes)?;
             let arg_end = tokens.position().1;
             let ret_type = if let Some(Token::Arrow) = tokens.next(false) {
                 Some(parse_expr(tokens, notes, false, false, None)?)
@@ -2192,7 +2664,9 @@ fn try_parse_macro(
 
             let m_value = ast::ValueBody::Macro(ast::Macro {
                 args,
-                body: ast::CompoundStatement { statements: body },
+                body: ast::CompoundStatement {
+                    statements: destructures.into_iter().chain(body).collect(),
+                },
                 properties: properties.clone(),
                 arg_pos: (arg_start, arg_end),
                 ret_type,
@@ -2473,7 +2947,10 @@ fn parse_variable(
             };
             ast::ValueBody::Str(inner)
         }
-        Some(Token::Id) => {
+        Some(Token::PatternVar) if !matches!(tokens.slice().as_str(), "?g" | "?c" | "?i" | "?b") => {
+            ast::ValueBody::PatternVar(LocalIntern::new(tokens.slice()[1..].to_string()))
+        }
+        Some(Token::Id | Token::PatternVar) => {
             let mut text = tokens.slice();
             let class_name = match text.pop().unwrap() {
                 'g' => ast::IdClass::Group,
@@ -111,6 +111,29 @@ pub fn get_config(opath: Option<PathBuf>) -> PathBuf {
     path
 }
 
+// an optional list of strings, like the builtins in `permissions`
+fn string_list(map: &YamlMap, key: &str) -> Result<Vec<String>, PckpError> {
+    match map.internal.get(&Yaml::from_str(key)) {
+        Some(Yaml::Array(list)) => list
+            .iter()
+            .map(|v| match v {
+                Yaml::String(s) => Ok(s.clone()),
+                b => Err(PckpError::config(
+                    format!("Expected list element {:?} in '{}' to be of type string", b, key),
+                    map.cfg.clone(),
+                    None,
+                )),
+            })
+            .collect(),
+        Some(_) => Err(PckpError::config(
+            format!("Expected key '{}' to be of type \"list\"", key),
+            map.cfg.clone(),
+            None,
+        )),
+        None => Ok(Vec::new()),
+    }
+}
+
 fn check_invalid(n: &str) -> Option<char> {
     let mut b = [0; 4];
     let potential_invalid: Vec<char> = n.chars().filter(|x| !String::from("qwertyuiopasdfghjklzxcvbnmQWERTYUIOPASDFGHJKLXCVBNM_-.0123456789").contains(&*x.encode_utf8(&mut b))).collect();
@@ -212,7 +235,8 @@ pub fn config_to_package(cfg: PathBuf) -> Result<Option<Package>, PckpError> {
                                         Yaml::String(s) => {
                                             Ok(Dependency {
                                                 source: DependencySource::Name(s),
-                                                version: "latest".to_string()
+                                                version: "latest".to_string(),
+                                                allow: Vec::new(),
                                             })
                                         },
                                         Yaml::Hash(h) => {
@@ -227,7 +251,8 @@ pub fn config_to_package(cfg: PathBuf) -> Result<Option<Package>, PckpError> {
                                                     enum DependencySource::Name, DependencySource::Url
                                                 )?,
 
-                                                version: yaml_to_str(&dmap.get_or_else("version", |_| Ok(Yaml::String("latest".to_string())))?)
+                                                version: yaml_to_str(&dmap.get_or_else("version", |_| Ok(Yaml::String("latest".to_string())))?),
+                                                allow: string_list(&dmap, "allow")?,
                                             })
                                         },
                                         c => Err(
@@ -243,7 +268,8 @@ pub fn config_to_package(cfg: PathBuf) -> Result<Option<Package>, PckpError> {
                             Yaml::String(s) => {
                                 vec![Dependency {
                                     source: DependencySource::Name(s),
-                                    version: "latest".to_string()
+                                    version: "latest".to_string(),
+                                    allow: Vec::new(),
                                 }]
                             },
                             Yaml::Hash(h) => {
@@ -258,7 +284,8 @@ pub fn config_to_package(cfg: PathBuf) -> Result<Option<Package>, PckpError> {
                                         enum DependencySource::Name, DependencySource::Url
                                     )?,
 
-                                    version: yaml_to_str(&dmap.get_or_else("version", |_| Ok(Yaml::String("latest".to_string())))?)
+                                    version: yaml_to_str(&dmap.get_or_else("version", |_| Ok(Yaml::String("latest".to_string())))?),
+                                    allow: string_list(&dmap, "allow")?,
                                 }]
                             },
                             c => return Err(
@@ -275,7 +302,9 @@ pub fn config_to_package(cfg: PathBuf) -> Result<Option<Package>, PckpError> {
                  .map(Package::dependency)
                  .collect::<Vec<_>>();
 
-                Ok(Some(Package::local(package_name, version, folders, depends)))
+                let permissions = string_list(&ymap, "permissions")?;
+
+                Ok(Some(Package::local(package_name, version, folders, depends, permissions)))
             },
             Err(_) => {
                 Err(PckpError::config("Could not open configuration file".to_string(), cfg, None))
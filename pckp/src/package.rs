@@ -11,6 +11,9 @@ use fs_extra::dir as fs_dir;
 
 pub const PACKAGE_DIR: &str = "pckp_libraries";
 
+// the builtins every installed package asked for and was allowed to use
+pub type GrantedPermissions = Vec<(String, Vec<String>)>;
+
 #[derive(PartialEq, Clone, Debug)]
 pub enum DependencySource {
     Name(String),
@@ -21,6 +24,9 @@ pub enum DependencySource {
 pub struct Dependency {
     pub source: DependencySource,
     pub version: String,
+    // builtins the user approved for this dependency and everything it pulls in (only
+    // read from the pckp.yaml of the project being built)
+    pub allow: Vec<String>,
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -29,6 +35,8 @@ pub struct LocalPackage {
     pub version: String,
     pub paths: Vec<PathBuf>,
     pub dependencies: Vec<Package>,
+    // builtins the package needs
+    pub permissions: Vec<String>,
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -48,6 +56,7 @@ impl Package {
         version: String,
         paths: Vec<PathBuf>,
         dependencies: Vec<Package>,
+        permissions: Vec<String>,
     ) -> Package {
         Package {
             internal: PackageType::Local(LocalPackage {
@@ -55,6 +64,7 @@ impl Package {
                 version,
                 paths,
                 dependencies,
+                permissions,
             }),
         }
     }
@@ -65,13 +75,14 @@ impl Package {
         }
     }
 
-    pub fn install_dependencies(&self, path: PathBuf) -> Result<(), PckpError> {
+    pub fn install_dependencies(&self, path: PathBuf) -> Result<GrantedPermissions, PckpError> {
         match &self.internal {
             PackageType::Local(root) => {
+                let mut granted = GrantedPermissions::new();
                 for x in &root.dependencies {
-                    x.install(&root.name, path.clone(), false)?;
+                    x.install(&root.name, path.clone(), false, None, &mut granted)?;
                 }
-                Ok(())
+                Ok(granted)
             }
             _ => unreachable!("ensure_local"),
         }
@@ -89,6 +100,10 @@ impl Package {
         parent_name: &str,
         path: PathBuf,
         ignore_version: bool,
+        // the builtins the user approved for the direct dependency this one is installed
+        // through, which also covers everything that dependency pulls in
+        approved: Option<&[String]>,
+        granted: &mut GrantedPermissions,
     ) -> Result<(), PckpError> {
         match &self.internal {
             PackageType::Local(p) => {
@@ -130,7 +145,7 @@ impl Package {
                 }
 
                 for dep in &p.dependencies {
-                    dep.install(&p.name, path.clone(), false)?;
+                    dep.install(&p.name, path.clone(), false, approved, granted)?;
                 }
 
                 export_version(version_info, &version_file);
@@ -182,7 +197,42 @@ impl Package {
                     ));
                 };
 
-                local_package.install(parent_name, path, d.version == "latest")
+                // only the user's own pckp.yaml can approve builtins, so a package can't
+                // grant them to its own dependencies
+                let direct = approved.is_none();
+                let approved = approved.unwrap_or(&d.allow);
+                if let PackageType::Local(p) = &local_package.internal {
+                    let missing = p
+                        .permissions
+                        .iter()
+                        .filter(|b| !approved.contains(b))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    if !missing.is_empty() {
+                        return Err(PckpError::custom_with_note(
+                            format!(
+                                "Package '{}' needs permission to use the builtins {}",
+                                p.name,
+                                missing.join(", ")
+                            ),
+                            Some(parent_name.to_string()),
+                            Some(format!(
+                                "Add `allow: [{}]` to the entry under `dependencies` in your pckp.yaml that {} to approve them",
+                                p.permissions.join(", "),
+                                if direct { "installs it" } else { "it is installed through" }
+                            )),
+                        ));
+                    }
+                    granted.push((p.name.clone(), p.permissions.clone()));
+                }
+
+                local_package.install(
+                    parent_name,
+                    path,
+                    d.version == "latest",
+                    Some(approved),
+                    granted,
+                )
                 //todo!("download and stuff");
             }
         }
@@ -1,5 +1,11 @@
-use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use compiler::builtins::{Group, Id};
+use compiler::compiler_types::{FunctionId, TriggerOrder};
+use compiler::leveldata::{GdObj, ObjParam};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
 use internment::{LocalIntern};
+use optimizer::optimize::optimize_with_threads;
+use optimizer::ReservedIds;
+use parser::ast::ObjectMode;
 use shared::SpwnSource;
 use std::fs;
 use spwn::parse_spwn;
@@ -8,13 +14,69 @@ fn test_all(input: &str, src: SpwnSource) {
     parse_spwn(input.to_string(), src, &[]).unwrap();
 }
 
+fn trigger(func_id: usize, unique_id: usize, params: Vec<(u16, ObjParam)>) -> (GdObj, TriggerOrder) {
+    let obj = GdObj {
+        func_id,
+        params: params.into_iter().collect(),
+        mode: ObjectMode::Trigger,
+        unique_id,
+        output: 0,
+    };
+    (obj, TriggerOrder(unique_id as f64))
+}
+
+// many independent chains of spawn triggers ending in a move trigger for an object,
+// which the optimizer can split up between threads
+fn trigger_chains(chains: u16) -> (Vec<FunctionId>, Vec<GdObj>, u16) {
+    let group = |n: u16| ObjParam::Group(Group { id: Id::Arbitrary(n) });
+    let mut func_ids = vec![FunctionId { parent: None, width: None, obj_list: Vec::new() }];
+    let mut objects = Vec::new();
+    let mut closed_group = 0;
+
+    for i in 0..chains as usize {
+        let (start, middle, end, target) = (closed_group + 1, closed_group + 2, closed_group + 3, closed_group + 4);
+        closed_group += 4;
+
+        let f = func_ids.len();
+        func_ids.push(FunctionId { parent: Some(0), width: None, obj_list: Vec::new() });
+
+        func_ids[0].obj_list.push(trigger(0, i * 4, vec![(1, ObjParam::Number(1268.0)), (51, group(start))]));
+        func_ids[f].obj_list.push(trigger(f, i * 4 + 1, vec![(1, ObjParam::Number(1268.0)), (57, group(start)), (51, group(middle))]));
+        func_ids[f].obj_list.push(trigger(f, i * 4 + 2, vec![(1, ObjParam::Number(1268.0)), (57, group(middle)), (51, group(end)), (63, ObjParam::Number(0.5))]));
+        func_ids[f].obj_list.push(trigger(f, i * 4 + 3, vec![(1, ObjParam::Number(901.0)), (57, group(end)), (51, group(target)), (28, ObjParam::Number(30.0))]));
+
+        objects.push(GdObj {
+            func_id: 0,
+            params: vec![(1, ObjParam::Number(1.0)), (57, group(target))].into_iter().collect(),
+            mode: ObjectMode::Object,
+            unique_id: i,
+            output: 0,
+        });
+    }
+
+    (func_ids, objects, closed_group)
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
-    let input = fs::read_to_string("test/test.spwn").unwrap();
+    let input = fs::read_to_string("../test/test.spwn").unwrap();
     let src = LocalIntern::new("".to_string());
 
     let mut bmg = c.benchmark_group("main");
     bmg.throughput(Throughput::Bytes(input.len() as u64));
     bmg.bench_function("test_all", |b| b.iter(|| test_all(&input, SpwnSource::String(src))));
+    bmg.finish();
+
+    let (func_ids, objects, closed_group) = trigger_chains(2000);
+
+    let mut bmg = c.benchmark_group("optimize");
+    bmg.sample_size(10);
+    for threads in [1, 2, 4, 8] {
+        bmg.bench_function(format!("{} threads", threads), |b| b.iter_batched(
+            || (func_ids.clone(), ReservedIds::from_objects(&objects, &func_ids)),
+            |(f, reserved)| optimize_with_threads(f, closed_group, reserved, threads),
+            BatchSize::LargeInput,
+        ));
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -2,6 +2,7 @@ pub use ::compiler::builtins;
 use ::compiler::builtins::BUILTIN_NAMES;
 
 use ::compiler::builtins::get_lib_file;
+use ::compiler::module_cache::ModuleCache;
 pub use ::compiler::compiler;
 pub use ::compiler::compiler_types;
 pub use ::compiler::context;
@@ -32,6 +33,8 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+pub mod test_runner;
+
 #[derive(Default)]
 pub struct SpwnCache {
     files: HashMap<SpwnSource, Source>,
@@ -81,6 +84,7 @@ pub fn run_spwn(
     };
 
     let mut std_out = Vec::<u8>::new();
+    let mut module_cache = ModuleCache::default();
 
     let mut compiled = match compiler::compile_spwn(
         statements,
@@ -90,6 +94,8 @@ pub fn run_spwn(
         Default::default(),
         "".to_string(),
         &mut std_out,
+        &mut module_cache,
+        Default::default(),
     ) {
         Ok(a) => a,
         Err(e) => {
@@ -114,7 +120,7 @@ pub fn run_spwn(
 
     objects.extend(compiled.objects);
 
-    let (new_ls, _) = leveldata::append_objects(objects, &String::new())?;
+    let (new_ls, _) = leveldata::append_objects(objects, &String::new(), &compiled.sync_ids)?;
 
     Ok([String::from_utf8_lossy(&std_out).to_string(), new_ls])
 }
@@ -132,15 +138,18 @@ pub fn run_all_doc_examples() {
 
     let permissions = builtins::BuiltinPermissions::new();
 
+    let mut module_cache = ModuleCache::default();
     let mut globals = globals::Globals::new(
         SpwnSource::File(globals_path),
         permissions.clone(),
         String::from(""),
         &mut std_out,
+        &mut module_cache,
     );
     globals.includes.push(PathBuf::from("./"));
 
     let mut start_context = context::FullContext::new(&globals);
+    start_context.set_as_root();
 
     let info = compiler_info::CompilerInfo::new();
 
@@ -256,7 +265,7 @@ fn add_tests(
     all_tests: &mut Vec<(String, String)>,
 ) {
     match val {
-        value::Value::Macro(m) => {
+        value::Value::Macro(value::Macro::FuncLike(m)) => {
             if let Some(example) = m.tag.get_example(true) {
                 all_tests.push((name, example));
             }
@@ -17,11 +17,17 @@ use ariadne::Cache;
 use optimize::optimize;
 
 use ::parser::parser::*;
-use builtins::BuiltinPermissions;
+use builtins::{BuiltinPermissions, FileAccess};
 
 use shared::SpwnSource;
+use spwn::test_runner::{self, TestOutcome};
 use spwn::SpwnCache;
 
+use ::compiler::compiler::CompileOptions;
+use ::compiler::compiler_types::FunctionId;
+use ::compiler::http_fixtures::HttpFixtures;
+use ::compiler::module_cache::ModuleCache;
+
 use std::path::PathBuf;
 
 use editorlive::editorlive::editor_paste;
@@ -56,6 +62,7 @@ fn eprint_with_color(text: &str, color: termcolor::Color) {
     stdout.set_color(&ColorSpec::new()).unwrap();
 }
 
+#[derive(Clone)]
 pub struct BuildOptions<'a> {
     permissions: BuiltinPermissions,
     include_paths: Vec<PathBuf>,
@@ -64,52 +71,111 @@ pub struct BuildOptions<'a> {
     level_name: Option<String>,
     live_editor: bool,
     save_file: Option<&'a str>,
+    emit_triggers: bool,
+    profile: Option<&'a str>,
+    max_contexts: Option<usize>,
+    trace_contexts: bool,
+    seed: Option<u64>,
+    http_fixtures: Option<HttpFixtures>,
+    // later ones override earlier ones
+    inputs: Vec<(String, String)>,
+    defines: Vec<(String, String)>,
+}
+
+// the options that `spwn test` shares with building
+fn include_paths_and_permissions(
+    cmd: &clap::ArgMatches,
+) -> Result<(Vec<PathBuf>, BuiltinPermissions), std::io::Error> {
+    let mut permissions = BuiltinPermissions::new();
+    let mut include_paths = vec![
+        std::env::current_dir().expect("Cannot access current directory"),
+        std::env::current_exe()?
+            .parent()
+            .expect("Executable must be in a directory")
+            .to_path_buf(),
+    ];
+
+    cmd.values_of("include-path")
+        .unwrap_or_default()
+        .for_each(|val| include_paths.push(val.into()));
+
+    cmd.values_of("allow").unwrap_or_default().for_each(|val| {
+        permissions.set(
+            val.parse()
+                .unwrap_or_else(|_| panic!("Invalid builtin name: {}", val)),
+            true,
+        )
+    });
+
+    for (flag, access) in [("allow-read", FileAccess::Read), ("allow-write", FileAccess::Write)] {
+        for dir in cmd.values_of(flag).unwrap_or_default() {
+            permissions.allow_path(access, dir.as_ref())?;
+        }
+    }
+
+    cmd.values_of("deny").unwrap_or_default().for_each(|val| {
+        permissions.set(
+            val.parse()
+                .unwrap_or_else(|_| panic!("Invalid builtin name: {}", val)),
+            false,
+        )
+    });
+    Ok((include_paths, permissions))
 }
 
 impl<'a> BuildOptions<'a> {
     fn from(build_cmd: &'a clap::ArgMatches) -> Result<Self, std::io::Error> {
-        let mut permissions = BuiltinPermissions::new();
-        let mut include_paths = vec![
-            std::env::current_dir().expect("Cannot access current directory"),
-            std::env::current_exe()?
-                .parent()
-                .expect("Executable must be in a directory")
-                .to_path_buf(),
-        ];
-
-        let gd_enabled =
-            !build_cmd.is_present("no-level") && !build_cmd.is_present("console-output");
+        let (include_paths, permissions) = include_paths_and_permissions(build_cmd)?;
+
+        let emit_triggers = build_cmd.value_of("emit") == Some("triggers");
+        let gd_enabled = !build_cmd.is_present("no-level")
+            && !build_cmd.is_present("console-output")
+            && !emit_triggers;
         let opti_enabled = !build_cmd.is_present("no-optimize");
         let level_name = build_cmd.value_of("level-name").map(str::to_string);
         let live_editor = build_cmd.is_present("live-editor");
         let save_file = build_cmd.value_of("save-file");
+        let profile = build_cmd.value_of("profile");
+        let max_contexts = build_cmd
+            .value_of("max-contexts")
+            .map(|n| n.parse().unwrap());
+        let trace_contexts = build_cmd.is_present("trace-contexts");
+        let seed = build_cmd.value_of("seed").map(|n| n.parse().unwrap());
+        let http_fixtures = build_cmd.value_of("http-fixtures").map(|dir| HttpFixtures {
+            dir: dir.into(),
+            record: build_cmd.is_present("record-http"),
+        });
+
+        let mut inputs = Vec::new();
+        if let Some(file) = build_cmd.value_of("inputs") {
+            for (i, line) in fs::read_to_string(file)?.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (key, value) = line.split_once('=').ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Line {} of {} is not a key=value pair", i + 1, file),
+                    )
+                })?;
+                inputs.push((key.trim().to_string(), value.trim().to_string()));
+            }
+        }
+        for input in build_cmd.values_of("input").unwrap_or_default() {
+            let (key, value) = input.split_once('=').unwrap();
+            inputs.push((key.trim().to_string(), value.to_string()));
+        }
 
-        build_cmd
-            .values_of("include-path")
+        let defines = build_cmd
+            .values_of("define")
             .unwrap_or_default()
-            .for_each(|val| include_paths.push(val.into()));
+            .map(|define| {
+                let (name, value) = define.split_once('=').unwrap();
+                (name.trim().to_string(), value.to_string())
+            })
+            .collect();
 
-        build_cmd
-            .values_of("allow")
-            .unwrap_or_default()
-            .for_each(|val| {
-                permissions.set(
-                    val.parse()
-                        .unwrap_or_else(|_| panic!("Invalid builtin name: {}", val)),
-                    true,
-                )
-            });
-
-        build_cmd
-            .values_of("deny")
-            .unwrap_or_default()
-            .for_each(|val| {
-                permissions.set(
-                    val.parse()
-                        .unwrap_or_else(|_| panic!("Invalid builtin name: {}", val)),
-                    false,
-                )
-            });
         Ok(BuildOptions {
             permissions,
             include_paths,
@@ -118,10 +184,71 @@ impl<'a> BuildOptions<'a> {
             level_name,
             live_editor,
             save_file,
+            emit_triggers,
+            profile,
+            max_contexts,
+            trace_contexts,
+            seed,
+            http_fixtures,
+            inputs,
+            defines,
         })
     }
 }
 
+// installs the dependencies in the script's pckp.yaml, and grants them the builtins
+// they were approved for
+#[cfg(not(target_arch = "wasm32"))]
+fn install_packages<'a>(script_path: &str, mut options: BuildOptions<'a>) -> BuildOptions<'a> {
+    let pckp_path = PathBuf::from(script_path).parent().unwrap().to_path_buf();
+    let cfg_file = config_file::get_config(Some(pckp_path.clone()));
+    let pckp_package = match config_file::config_to_package(cfg_file) {
+        Ok(p) => p,
+        Err(e) => {
+            eprint_with_color(&format!("Error reading pckp file:\n{e}"), Color::Red);
+
+            std::process::exit(ERROR_EXIT_CODE);
+        }
+    };
+    if let Some(pack) = pckp_package {
+        match pack.install_dependencies(pckp_path) {
+            Ok(granted) => {
+                for (package, names) in granted {
+                    let mut builtins = Vec::new();
+                    for name in names {
+                        match name.parse() {
+                            Ok(b) => builtins.push(b),
+                            Err(_) => {
+                                eprint_with_color(
+                                    &format!("Error reading pckp file:\nPackage '{package}' asks for permission to use {name}, which is not a builtin"),
+                                    Color::Red,
+                                );
+
+                                std::process::exit(ERROR_EXIT_CODE);
+                            }
+                        }
+                    }
+                    options.permissions.grant_package(package, builtins);
+                }
+            }
+            Err(e) => {
+                eprint_with_color(
+                    &format!("Error installing dependencies:\n{e}"),
+                    Color::Red,
+                );
+
+                std::process::exit(ERROR_EXIT_CODE);
+            }
+        }
+    }
+    options
+}
+
+#[cfg(target_arch = "wasm32")]
+fn install_packages<'a>(_script_path: &str, options: BuildOptions<'a>) -> BuildOptions<'a> {
+    options
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let matches = Command::new("SPWN")
     .arg_required_else_help(true)
@@ -141,6 +268,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     arg!(-i --"include-path" "Adds a search path to look for libraries").takes_value(true).multiple_occurrences(true).min_values(0),
                     arg!(-a --allow "Allow the use of a builtin").takes_value(true).multiple_occurrences(true).min_values(0),
                     arg!(-d --deny "Deny the use of a builtin").takes_value(true).multiple_occurrences(true).min_values(0),
+                    arg!(--"allow-read" <DIR> "Allow the builtins that read files, but only inside this directory").required(false).multiple_occurrences(true).value_hint(ValueHint::DirPath),
+                    arg!(--"allow-write" <DIR> "Allow the builtins that write or delete files, but only inside this directory").required(false).multiple_occurrences(true).value_hint(ValueHint::DirPath),
+                    arg!(--emit <FORMAT> "Prints the compiled output in a human-readable format instead of creating a level").required(false).possible_values(["triggers"]),
+                    arg!(--profile <FILE> "Records how long each macro call, import and garbage collection takes, and writes it to a folded stack file that can be made into a flamegraph").required(false).value_hint(ValueHint::FilePath),
+                    arg!(--"max-contexts" [N] "The most contexts that can exist at once before the build is stopped (no limit by default)").validator(|n| n.parse::<usize>()),
+                    arg!(--"trace-contexts" "Prints every time the context is split or merged"),
+                    arg!(--seed <SEED> "Makes $.random give the same results every build (overrides the #[seed] attribute)").required(false).validator(|n| n.parse::<u64>()),
+                    arg!(--"http-fixtures" <DIR> "Answers $.http_request with the responses recorded in this directory instead of going to the network").required(false).value_hint(ValueHint::DirPath),
+                    arg!(--"record-http" "Makes the real requests and records their responses in the --http-fixtures directory").requires("http-fixtures"),
+                    arg!(--input <INPUT> "Answers a $.get_input call without asking, as key=value (where the key is the input key or the prompt)").required(false).multiple_occurrences(true).validator(|s| if s.contains('=') { Ok(()) } else { Err("expected key=value") }),
+                    arg!(--inputs <FILE> "Reads answers for $.get_input from a file with a key=value pair on every line").required(false).value_hint(ValueHint::FilePath),
+                    arg!(-D --define <DEFINE> "Gives the script a value in $.defines, as name=value (the value can be a number, true/false or a string)").required(false).multiple_occurrences(true).validator(|s| if s.contains('=') { Ok(()) } else { Err("expected name=value") }),
+                    arg!(-w --watch "Builds the script again whenever one of its files changes. The imported modules that didn't change are reused from the previous build (this cache is only kept while watching)"),
                 ]),
 
             Command::new("eval")
@@ -156,6 +296,31 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     arg!(-i --"include-path" "Adds a search path to look for libraries").takes_value(true).multiple_occurrences(true).min_values(0),
                     arg!(-a --allow "Allow the use of a builtin").takes_value(true).multiple_occurrences(true).min_values(0),
                     arg!(-d --deny "Deny the use of a builtin").takes_value(true).multiple_occurrences(true).min_values(0),
+                    arg!(--"allow-read" <DIR> "Allow the builtins that read files, but only inside this directory").required(false).multiple_occurrences(true).value_hint(ValueHint::DirPath),
+                    arg!(--"allow-write" <DIR> "Allow the builtins that write or delete files, but only inside this directory").required(false).multiple_occurrences(true).value_hint(ValueHint::DirPath),
+                    arg!(--emit <FORMAT> "Prints the compiled output in a human-readable format instead of creating a level").required(false).possible_values(["triggers"]),
+                    arg!(--profile <FILE> "Records how long each macro call, import and garbage collection takes, and writes it to a folded stack file that can be made into a flamegraph").required(false).value_hint(ValueHint::FilePath),
+                    arg!(--"max-contexts" [N] "The most contexts that can exist at once before the build is stopped (no limit by default)").validator(|n| n.parse::<usize>()),
+                    arg!(--"trace-contexts" "Prints every time the context is split or merged"),
+                    arg!(--seed <SEED> "Makes $.random give the same results every build (overrides the #[seed] attribute)").required(false).validator(|n| n.parse::<u64>()),
+                    arg!(--"http-fixtures" <DIR> "Answers $.http_request with the responses recorded in this directory instead of going to the network").required(false).value_hint(ValueHint::DirPath),
+                    arg!(--"record-http" "Makes the real requests and records their responses in the --http-fixtures directory").requires("http-fixtures"),
+                    arg!(--input <INPUT> "Answers a $.get_input call without asking, as key=value (where the key is the input key or the prompt)").required(false).multiple_occurrences(true).validator(|s| if s.contains('=') { Ok(()) } else { Err("expected key=value") }),
+                    arg!(--inputs <FILE> "Reads answers for $.get_input from a file with a key=value pair on every line").required(false).value_hint(ValueHint::FilePath),
+                    arg!(-D --define <DEFINE> "Gives the script a value in $.defines, as name=value (the value can be a number, true/false or a string)").required(false).multiple_occurrences(true).validator(|s| if s.contains('=') { Ok(()) } else { Err("expected name=value") }),
+                ]),
+
+            Command::new("test")
+                .about("Runs the macros marked with #[test] in the given files, or in all spwn files in the given folders")
+                .visible_alias("t")
+                .args(&[
+                    arg!([PATH] ... "Files or folders to look for tests in (default: the current folder)").value_hint(ValueHint::AnyPath),
+                    arg!(-f --filter <TEXT> "Only runs the tests with this text in their name").required(false),
+                    arg!(-i --"include-path" "Adds a search path to look for libraries").takes_value(true).multiple_occurrences(true).min_values(0),
+                    arg!(-a --allow "Allow the use of a builtin").takes_value(true).multiple_occurrences(true).min_values(0),
+                    arg!(-d --deny "Deny the use of a builtin").takes_value(true).multiple_occurrences(true).min_values(0),
+                    arg!(--"allow-read" <DIR> "Allow the builtins that read files, but only inside this directory").required(false).multiple_occurrences(true).value_hint(ValueHint::DirPath),
+                    arg!(--"allow-write" <DIR> "Allow the builtins that write or delete files, but only inside this directory").required(false).multiple_occurrences(true).value_hint(ValueHint::DirPath),
                 ]),
 
             Command::new("doc")
@@ -176,38 +341,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     if let Some(build_cmd) = matches.subcommand_matches("build") {
         let script_path = build_cmd.value_of("SCRIPT").ok_or("unreachable")?;
 
-        let options = BuildOptions::from(build_cmd)?;
+        let options = install_packages(script_path, BuildOptions::from(build_cmd)?);
         let source = SpwnSource::File(script_path.into());
         let unparsed = fs::read_to_string(script_path)?;
 
-        let pckp_path = PathBuf::from(script_path).parent().unwrap().to_path_buf();
-        let cfg_file = config_file::get_config(Some(pckp_path.clone()));
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            let pckp_package = match config_file::config_to_package(cfg_file) {
-                Ok(p) => p,
-                Err(e) => {
-                    eprint_with_color(&format!("Error reading pckp file:\n{e}"), Color::Red);
-
-                    std::process::exit(ERROR_EXIT_CODE);
-                }
-            };
-            if let Some(pack) = pckp_package {
-                match pack.install_dependencies(pckp_path) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        eprint_with_color(
-                            &format!("Error installing dependencies:\n{e}"),
-                            Color::Red,
-                        );
-
-                        std::process::exit(ERROR_EXIT_CODE);
-                    }
-                }
-            }
+        if build_cmd.is_present("watch") {
+            watch(script_path, options)
+        } else {
+            exit_on_failure(build_spwn_source(
+                source,
+                unparsed,
+                options,
+                &mut ModuleCache::default(),
+            ))
         }
-
-        build_spwn_source(source, unparsed, options)
     } else if let Some(eval_cmd) = matches.subcommand_matches("eval") {
         use ariadne::Color::{Blue, Red};
         let end_command = ":build";
@@ -228,7 +375,27 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let options = BuildOptions::from(eval_cmd)?;
         let source = SpwnSource::String(internment::LocalIntern::from(unparsed));
 
-        build_spwn_source(source, unparsed.to_string(), options)
+        exit_on_failure(build_spwn_source(
+            source,
+            unparsed.to_string(),
+            options,
+            &mut ModuleCache::default(),
+        ))
+    } else if let Some(test_cmd) = matches.subcommand_matches("test") {
+        let (include_paths, permissions) = include_paths_and_permissions(test_cmd)?;
+        let paths = match test_cmd.values_of("PATH") {
+            Some(p) => p.map(PathBuf::from).collect(),
+            None => vec![PathBuf::from(".")],
+        };
+        if !run_tests(
+            &paths,
+            test_cmd.value_of("filter"),
+            include_paths,
+            permissions,
+        )? {
+            std::process::exit(ERROR_EXIT_CODE);
+        }
+        Ok(())
     } else if let Some(doc_cmd) = matches.subcommand_matches("doc") {
         let lib_path = doc_cmd.value_of("LIBRARY").unwrap();
         if "$" == lib_path {
@@ -316,10 +483,149 @@ folders:
     }
 }
 
+// the errors have already been reported when this is returned
+#[derive(Debug)]
+struct BuildFailed;
+
+impl std::fmt::Display for BuildFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Build failed")
+    }
+}
+
+impl std::error::Error for BuildFailed {}
+
+fn exit_on_failure(
+    result: Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match result {
+        Err(e) if e.is::<BuildFailed>() => std::process::exit(ERROR_EXIT_CODE),
+        r => r,
+    }
+}
+
+// runs every test it can find, and returns whether they all passed
+fn run_tests(
+    paths: &[PathBuf],
+    filter: Option<&str>,
+    include_paths: Vec<PathBuf>,
+    permissions: BuiltinPermissions,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut passed = 0;
+    let mut failures = Vec::new();
+    let mut broken_files = 0;
+
+    for file in test_runner::find_test_files(paths)? {
+        let code = fs::read_to_string(&file)?;
+        let source = SpwnSource::File(file.clone());
+        let tests = match parse_spwn(code.clone(), source, builtins::BUILTIN_NAMES) {
+            Ok((statements, _)) => test_runner::find_tests(&statements),
+            Err(err) => {
+                create_report(ErrorReport::from(err))
+                    .eprint(SpwnCache::default())
+                    .unwrap();
+                broken_files += 1;
+                continue;
+            }
+        };
+        let tests = tests
+            .into_iter()
+            .filter(|t| match filter {
+                Some(f) => t.name.contains(f),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        if tests.is_empty() {
+            continue;
+        }
+
+        println!(
+            "\nrunning {} test{} in {}",
+            tests.len(),
+            if tests.len() == 1 { "" } else { "s" },
+            file.display()
+        );
+        for test in tests {
+            print!("test {} ... ", test.name);
+            std::io::stdout().flush()?;
+            match test_runner::run_test(
+                &file,
+                &code,
+                &test,
+                include_paths.clone(),
+                permissions.clone(),
+            ) {
+                TestOutcome::Passed => {
+                    print_with_color("ok", Color::Green);
+                    passed += 1;
+                }
+                TestOutcome::Failed(message) => {
+                    print_with_color("FAILED", Color::Red);
+                    failures.push((format!("{} ({})", test.name, file.display()), message));
+                }
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for (name, message) in &failures {
+            print_with_color(&format!("\n---- {} ----", name), Color::Red);
+            println!("{}", message.trim_end());
+        }
+    }
+    if broken_files > 0 {
+        eprint_with_color(
+            &format!("\n{} files could not be parsed", broken_files),
+            Color::Red,
+        );
+    }
+
+    let ok = failures.is_empty() && broken_files == 0;
+    let summary = format!(
+        "\ntest result: {}. {} passed; {} failed",
+        if ok { "ok" } else { "FAILED" },
+        passed,
+        failures.len()
+    );
+    print_with_color(&summary, if ok { Color::Green } else { Color::Red });
+    Ok(ok)
+}
+
+// builds the script again every time one of the files it uses changes,
+// reusing the imported modules that are still the same
+fn watch(script_path: &str, options: BuildOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut module_cache = ModuleCache::default();
+    loop {
+        let source = SpwnSource::File(script_path.into());
+        let unparsed = fs::read_to_string(script_path)?;
+        match build_spwn_source(source, unparsed, options.clone(), &mut module_cache) {
+            Err(e) if !e.is::<BuildFailed>() => eprint_with_color(&e.to_string(), Color::Red),
+            _ => (),
+        }
+
+        let mut files = module_cache.files();
+        files.push(PathBuf::from(script_path));
+        let modified = || {
+            files
+                .iter()
+                .map(|f| fs::metadata(f).and_then(|m| m.modified()).ok())
+                .collect::<Vec<_>>()
+        };
+
+        print_with_color("\nWatching for changes...", Color::Cyan);
+        let last_modified = modified();
+        while modified() == last_modified {
+            std::thread::sleep(std::time::Duration::from_millis(300));
+        }
+    }
+}
+
 fn build_spwn_source(
     source: SpwnSource,
     unparsed: String,
     mut options: BuildOptions,
+    module_cache: &mut ModuleCache,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut cache = SpwnCache::default();
     match cache.fetch(&source) {
@@ -336,7 +642,7 @@ fn build_spwn_source(
     ) {
         Err(err) => {
             create_report(ErrorReport::from(err)).eprint(cache).unwrap();
-            std::process::exit(ERROR_EXIT_CODE);
+            return Err(Box::new(BuildFailed));
         }
         Ok(p) => p,
     };
@@ -370,33 +676,36 @@ fn build_spwn_source(
     } else {
         None
     };
-    let level_string = if options.gd_enabled {
-        if let Some(gd_path) = &gd_path {
+    let save_content = match &gd_path {
+        Some(gd_path) => {
             print_with_color("Reading savefile...", Color::Cyan);
             let mut file = fs::File::open(gd_path)?;
             let mut file_content = Vec::new();
             file.read_to_end(&mut file_content)
                 .expect("Problem reading savefile");
-            let mut level_string =
-                match levelstring::get_level_string(file_content, options.level_name.as_ref()) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        eprint_with_color(&format!("Error reading level:\n{}", e), Color::Red);
+            Some(file_content)
+        }
+        None => None,
+    };
+    let read_level = |level_name: Option<&String>| -> Result<String, Box<dyn std::error::Error>> {
+        match &save_content {
+            Some(content) => match levelstring::get_level_string(content.clone(), level_name) {
+                Ok(mut level_string) => {
+                    leveldata::remove_spwn_objects(&mut level_string);
+                    Ok(level_string)
+                }
+                Err(e) => {
+                    eprint_with_color(&format!("Error reading level:\n{}", e), Color::Red);
 
-                        std::process::exit(ERROR_EXIT_CODE);
-                    }
-                };
-            if level_string.is_empty() {}
-            leveldata::remove_spwn_objects(&mut level_string);
-            level_string
-        } else {
-            String::new()
+                    Err(Box::new(BuildFailed))
+                }
+            },
+            None => Ok(String::new()),
         }
-    } else {
-        String::new()
     };
+    let level_string = read_level(options.level_name.as_ref())?;
     let mut std_out = std::io::stdout();
-    let mut compiled = match compiler::compile_spwn(
+    let compiled = match compiler::compile_spwn(
         statements,
         source,
         options.include_paths,
@@ -404,74 +713,158 @@ fn build_spwn_source(
         options.permissions,
         level_string.clone(),
         &mut std_out,
+        module_cache,
+        CompileOptions {
+            profile: options.profile.is_some(),
+            max_contexts: options.max_contexts,
+            trace_contexts: options.trace_contexts,
+            seed: options.seed,
+            http_fixtures: options.http_fixtures,
+            inputs: options.inputs.into_iter().collect(),
+            defines: options.defines,
+            quiet: false,
+        },
     ) {
         Err(err) => {
             create_report(ErrorReport::from(err)).eprint(cache).unwrap();
-            std::process::exit(ERROR_EXIT_CODE);
+            return Err(Box::new(BuildFailed));
         }
         Ok(p) => p,
     };
-    if options.gd_enabled {
-        let reserved = optimizer::ReservedIds::from_objects(&compiled.objects, &compiled.func_ids);
 
-        let has_stuff = compiled.func_ids.iter().any(|x| !x.obj_list.is_empty());
-        if options.opti_enabled && has_stuff {
-            print_with_color("Optimizing triggers...", Color::Cyan);
-            compiled.func_ids = optimize(compiled.func_ids, compiled.closed_groups, reserved);
+    if let (Some(path), Some(profiler)) = (options.profile, &compiled.profiler) {
+        fs::write(path, profiler.folded())?;
+        print_with_color("\nSlowest parts of the build:", Color::Magenta);
+        println!("{}", profiler.summary(15));
+        print_with_color(&format!("Profile written to {}", path), Color::Green);
+    }
+    if options.gd_enabled || options.emit_triggers {
+        // the main level, then the levels the script switched to with $.output_level
+        let mut levels = vec![(options.level_name.clone(), level_string)];
+        let mut targets = vec![0];
+        for name in &compiled.output_levels {
+            if Some(name) == options.level_name.as_ref() {
+                targets.push(0);
+            } else {
+                targets.push(levels.len());
+                levels.push((Some(name.clone()), read_level(Some(name))?));
+            }
         }
 
-        let mut objects = leveldata::apply_fn_ids(&compiled.func_ids);
+        // every level gets the same function ids, with only its own triggers in them
+        let empty_func_ids = compiled
+            .func_ids
+            .iter()
+            .map(|f| FunctionId {
+                obj_list: Vec::new(),
+                ..f.clone()
+            })
+            .collect::<Vec<_>>();
+        let mut level_func_ids = vec![empty_func_ids; levels.len()];
+        let mut level_objects = vec![Vec::new(); levels.len()];
+        for (f, func) in compiled.func_ids.into_iter().enumerate() {
+            for (obj, order) in func.obj_list {
+                level_func_ids[targets[obj.output]][f]
+                    .obj_list
+                    .push((obj, order));
+            }
+        }
+        for obj in compiled.objects {
+            level_objects[targets[obj.output]].push(obj);
+        }
 
-        objects.extend(compiled.objects);
+        if options.live_editor && levels.len() > 1 {
+            eprint_with_color(
+                "Only the main level can be pasted into the editor, the other output levels are skipped",
+                Color::Yellow,
+            );
+            levels.truncate(1);
+        }
 
-        print_with_color(&format!("{} objects added", objects.len()), Color::White);
+        let multiple = levels.len() > 1;
+        for (((level_name, level_string), mut func_ids), level_objects) in levels
+            .into_iter()
+            .zip(level_func_ids)
+            .zip(level_objects)
+        {
+            let title = match (&level_name, multiple) {
+                (Some(name), true) => format!(" ({})", name),
+                _ => String::new(),
+            };
 
-        let (new_ls, used_ids) = leveldata::append_objects(objects, &level_string)?;
+            let reserved = optimizer::ReservedIds::from_objects(&level_objects, &func_ids);
 
-        print_with_color("\nLevel:", Color::Magenta);
-        for (i, len) in used_ids.iter().enumerate() {
-            if *len > 0 {
-                print_with_color(
-                    &format!(
-                        "{} {}",
-                        len,
-                        ["groups", "colors", "block IDs", "item IDs"][i]
-                    ),
-                    Color::White,
-                );
+            let has_stuff = func_ids.iter().any(|x| !x.obj_list.is_empty());
+            if options.opti_enabled && has_stuff {
+                print_with_color(&format!("Optimizing triggers{}...", title), Color::Cyan);
+                func_ids = optimize(func_ids, compiled.closed_groups, reserved);
             }
-        }
-        //println!("level_string: {}", level_string);
-        if options.live_editor {
-            match editor_paste(&new_ls) {
-                Err(e) => {
-                    eprint_with_color(&format!("Error pasting into editor:\n{}", e), Color::Red);
 
-                    std::process::exit(ERROR_EXIT_CODE);
-                }
-                Ok(_) => {
-                    print_with_color("Pasted into the editor!", Color::Green);
-                }
+            if options.emit_triggers {
+                let listing = leveldata::trigger_listing(
+                    &func_ids,
+                    &level_objects,
+                    &compiled.obj_prop_names,
+                    &compiled.sync_ids,
+                )?;
+                print_with_color(&format!("\nTriggers{}:", title), Color::Magenta);
+                println!("{}", listing);
+                continue;
             }
-        } else {
-            match gd_path {
-                Some(gd_path) => {
-                    print_with_color("\nWriting back to savefile...", Color::Cyan);
-                    levelstring::encrypt_level_string(
-                        new_ls,
-                        level_string,
-                        gd_path,
-                        options.level_name,
-                    )?;
 
+            let mut objects = leveldata::apply_fn_ids(&func_ids);
+
+            objects.extend(level_objects);
+
+            print_with_color(&format!("{} objects added", objects.len()), Color::White);
+
+            let (new_ls, used_ids) = leveldata::append_objects(objects, &level_string, &compiled.sync_ids)?;
+
+            print_with_color(&format!("\nLevel{}:", title), Color::Magenta);
+            for (i, len) in used_ids.iter().enumerate() {
+                if *len > 0 {
                     print_with_color(
-                        "Written to save. You can now open Geometry Dash again!",
-                        Color::Green,
+                        &format!(
+                            "{} {}",
+                            len,
+                            ["groups", "colors", "block IDs", "item IDs"][i]
+                        ),
+                        Color::White,
                     );
                 }
+            }
+            //println!("level_string: {}", level_string);
+            if options.live_editor {
+                match editor_paste(&new_ls) {
+                    Err(e) => {
+                        eprint_with_color(&format!("Error pasting into editor:\n{}", e), Color::Red);
 
-                None => println!("Output: {}", new_ls),
-            };
+                        return Err(Box::new(BuildFailed));
+                    }
+                    Ok(_) => {
+                        print_with_color("Pasted into the editor!", Color::Green);
+                    }
+                }
+            } else {
+                match &gd_path {
+                    Some(gd_path) => {
+                        print_with_color("\nWriting back to savefile...", Color::Cyan);
+                        levelstring::encrypt_level_string(
+                            new_ls,
+                            level_string,
+                            gd_path.clone(),
+                            level_name,
+                        )?;
+
+                        print_with_color(
+                            "Written to save. You can now open Geometry Dash again!",
+                            Color::Green,
+                        );
+                    }
+
+                    None => println!("Output{}: {}", title, new_ls),
+                };
+            }
         }
     };
     let mut stdout = StandardStream::stdout(ColorChoice::Always);
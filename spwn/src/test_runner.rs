@@ -0,0 +1,187 @@
+// runs the #[test] macros in spwn files, for `spwn test`
+
+use ::compiler::builtins::{BuiltinPermissions, BUILTIN_NAMES};
+use ::compiler::compiler::{compile_spwn, CompileOptions};
+use ::compiler::module_cache::ModuleCache;
+use ::parser::ast;
+use ::parser::parser::parse_spwn;
+use ariadne::Source;
+use errors::{create_report, ErrorReport};
+use shared::SpwnSource;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::SpwnCache;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCase {
+    pub name: String,
+    // #[test, expect_output("...")]: what the test has to print
+    pub expect_output: Option<String>,
+    // #[test, expect_error] or #[test, expect_error("...")]: the test has to fail to compile,
+    // with an error that contains the text if there is one
+    pub expect_error: Option<Option<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TestOutcome {
+    Passed,
+    Failed(String),
+}
+
+// every .spwn file in the given files and folders
+pub fn find_test_files(paths: &[PathBuf]) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .map(|e| e.map(|e| e.path()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            entries.sort();
+            for entry in entries {
+                if entry.is_dir() {
+                    files.extend(find_test_files(&[entry])?);
+                } else if entry.extension().and_then(|e| e.to_str()) == Some("spwn") {
+                    files.push(entry);
+                }
+            }
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn string_arg(args: &[ast::Argument]) -> Option<String> {
+    match &args.first()?.value.values.first()?.value.body {
+        ast::ValueBody::Str(s) => Some(s.inner.clone()),
+        _ => None,
+    }
+}
+
+// the macros defined at the top of the file with a #[test] attribute
+pub fn find_tests(statements: &[ast::Statement]) -> Vec<TestCase> {
+    let mut tests = Vec::new();
+    for statement in statements {
+        if let ast::StatementBody::Definition(ast::Definition {
+            symbol,
+            value: Some(value),
+            ..
+        }) = &statement.body
+        {
+            let name = match &symbol.value.body {
+                ast::ValueBody::Symbol(s) if symbol.path.is_empty() => s.as_ref().clone(),
+                _ => continue,
+            };
+            if let [ast::Variable {
+                value:
+                    ast::ValueLiteral {
+                        body: ast::ValueBody::Macro(m),
+                    },
+                path,
+                ..
+            }] = &value.values[..]
+            {
+                if !path.is_empty() || m.properties.get("test").is_none() {
+                    continue;
+                }
+                tests.push(TestCase {
+                    name,
+                    expect_output: m.properties.get("expect_output").and_then(|a| string_arg(&a)),
+                    expect_error: m.properties.get("expect_error").map(|a| string_arg(&a)),
+                });
+            }
+        }
+    }
+    tests
+}
+
+// compiles the file again with a call to the test at the end, so every test
+// starts from a clean state
+pub fn run_test(
+    path: &Path,
+    code: &str,
+    test: &TestCase,
+    included: Vec<PathBuf>,
+    permissions: BuiltinPermissions,
+) -> TestOutcome {
+    let code = format!("{}\n{}()\n", code, test.name);
+    let source = SpwnSource::File(path.to_path_buf());
+
+    // the errors have to be shown with the call that was added
+    let mut cache = SpwnCache::default();
+    cache
+        .files
+        .insert(source.clone(), Source::from(code.clone()));
+    let render = |report: ErrorReport, cache: SpwnCache| {
+        let mut out = Vec::<u8>::new();
+        create_report(report).write(cache, &mut out).unwrap();
+        String::from_utf8_lossy(&out).to_string()
+    };
+
+    let (statements, notes) = match parse_spwn(code.clone(), source.clone(), BUILTIN_NAMES) {
+        Ok(a) => a,
+        Err(e) => return TestOutcome::Failed(render(ErrorReport::from(e), cache)),
+    };
+
+    let mut std_out = Vec::<u8>::new();
+    let result = compile_spwn(
+        statements,
+        source,
+        included,
+        notes,
+        permissions,
+        String::new(),
+        &mut std_out,
+        &mut ModuleCache::default(),
+        CompileOptions {
+            quiet: true,
+            ..Default::default()
+        },
+    )
+    .map(|_| ());
+    let output = String::from_utf8_lossy(&std_out).to_string();
+
+    match (result, &test.expect_error) {
+        (Ok(()), Some(_)) if output.is_empty() => {
+            TestOutcome::Failed("expected an error, but the test succeeded".to_string())
+        }
+        (Ok(()), Some(_)) => TestOutcome::Failed(format!(
+            "expected an error, but the test succeeded\noutput:\n{}",
+            output
+        )),
+        (Err(e), None) => TestOutcome::Failed(format!(
+            "{}{}",
+            output,
+            render(ErrorReport::from(e), cache)
+        )),
+        (Err(e), Some(expected)) => {
+            let report = ErrorReport::from(e);
+            match expected {
+                Some(text)
+                    if !report.message.contains(text.as_str())
+                        && !report.labels.iter().any(|(_, l)| l.contains(text.as_str())) =>
+                {
+                    TestOutcome::Failed(format!(
+                        "expected an error containing \"{}\", but got:\n{}",
+                        text,
+                        render(report, cache)
+                    ))
+                }
+                _ => TestOutcome::Passed,
+            }
+        }
+        (Ok(()), None) => match &test.expect_output {
+            Some(expected) if output.trim_end() != expected.trim_end() => {
+                TestOutcome::Failed(format!(
+                    "output did not match\nexpected:\n{}\nfound:\n{}",
+                    expected.trim_end(),
+                    output.trim_end()
+                ))
+            }
+            _ => TestOutcome::Passed,
+        },
+    }
+}
+
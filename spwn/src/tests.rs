@@ -5,11 +5,12 @@ use std::path::PathBuf;
 use crate::run_spwn;
 
 macro_rules! run_test {
-    {$([$attr:ident])? NAME: $name:ident CODE: $code:literal $(OUTPUT: $output:literal)?} => {
+    {$([$attr:ident])? NAME: $name:ident $(INCLUDE: $include:literal)? CODE: $code:literal $(OUTPUT: $output:literal)?} => {
         #[test]
         $(#[$attr])?
         fn $name() {
-            let res = match run_spwn($code.to_string(), vec![PathBuf::from("./")], false) {
+            let includes = vec![PathBuf::from("./") $(, PathBuf::from($include))?];
+            let res = match run_spwn($code.to_string(), includes, false) {
                 Ok(a) => a,
                 Err(e) => {
                     eprintln!("{}", e);
@@ -118,3 +119,1130 @@ true
 true
     "
 }
+
+// serialize
+run_test! {
+    NAME: serialize
+    CODE: r"
+#[no_std]
+data = {name: 'level', ids: [1, 2.5], nothing: null}
+$.print($.serialize(data, 'json'))
+$.print($.serialize({a: 1, b: {c: true}}, 'toml'))
+$.print($.serialize({g: 3g, f: !{}}, 'json', true))
+    "
+    OUTPUT: r#"
+{"ids":[1,2.5],"name":"level","nothing":null}
+a = 1
+
+[b]
+c = true
+
+{"f":{"$type":"@trigger_function","start_group":{"$type":"@group","arbitrary":1}},"g":{"$type":"@group","id":3}}
+    "#
+}
+
+// random
+run_test! {
+    NAME: seeded_random
+    CODE: r"
+#[no_std, seed(42)]
+$.print($.random(1..1000))
+$.print($.random([1, 2, 3], 3))
+    "
+    OUTPUT: r"
+224
+[3, 1, 3]
+    "
+}
+
+// try/catch
+run_test! {
+    NAME: try_catch
+    CODE: r"
+#[no_std]
+let a = 1
+try {
+    a = 2
+    throw 'oh no'
+} catch e {
+    $.print(e.kind, ': ', e.message, ' ', a)
+}
+try {
+    $.assert(false)
+} catch e {
+    $.print(e.kind, ': ', e.message)
+}
+try {
+    $.print('fine')
+} catch {
+    $.print('not run')
+}
+    "
+    OUTPUT: r"
+throw: oh no 2
+builtin: Assertion failed
+fine
+    "
+}
+
+// what a failed try body added to the level is taken out again
+#[test]
+fn try_catch_level() {
+    use crate::compiler::{compile_spwn, CompileOptions};
+    use crate::ModuleCache;
+    use shared::SpwnSource;
+
+    let code = r"
+#[no_std]
+try {
+    $.add(obj {1: 1, 2: 15, 3: 15})
+    $.add(trigger {1: 1268, 51: ?g})
+    f = !{
+        $.add(trigger {1: 1268, 51: 20g})
+    }
+    f!
+    throw 'no'
+} catch {}
+$.add(trigger {1: 1268, 51: ?g})
+";
+    let source = SpwnSource::String(internment::LocalIntern::new(code.to_string()));
+    let (statements, notes) =
+        crate::parse_spwn(code.to_string(), source.clone(), crate::builtins::BUILTIN_NAMES)
+            .unwrap();
+    let mut std_out = Vec::<u8>::new();
+    let mut module_cache = ModuleCache::default();
+    let compiled = compile_spwn(
+        statements,
+        source,
+        vec![PathBuf::from("./")],
+        notes,
+        Default::default(),
+        String::new(),
+        &mut std_out,
+        &mut module_cache,
+        CompileOptions {
+            quiet: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert!(compiled.objects.is_empty());
+    assert_eq!(compiled.func_ids.len(), 1);
+    let triggers = compiled.func_ids[0]
+        .obj_list
+        .iter()
+        .map(|(obj, _)| format!("{:?}", obj.params[&51]))
+        .collect::<Vec<_>>();
+    // the groups made in the try body (one of them for f) aren't given out again
+    assert_eq!(triggers, ["Group(3?g)"]);
+    assert_eq!(compiled.closed_groups, 3);
+}
+
+// ids made in a failed try body can still be stored outside of it
+run_test! {
+    NAME: try_catch_ids
+    CODE: r"
+#[no_std]
+let group = 0g
+let item = 0i
+try {
+    group = ?g
+    item = ?i
+    throw 'no'
+} catch {}
+$.print(group == ?g, ' ', item == ?i, ' ', group == ?g)
+    "
+    OUTPUT: r"
+false false false
+    "
+}
+
+// f-strings
+run_test! {
+    NAME: format_strings
+    CODE: r#"
+#[no_std]
+name = 'world'
+n = 3.14159
+$.print(f"hello {name}! {n:.2} {{}}")
+$.print(f"[{n:>8.1}] [{name:*^9}] [{-12:06}] [{[1, 2]}]")
+    "#
+    OUTPUT: r"
+hello world! 3.14 {}
+[     3.1] [**world**] [-00012] [[1, 2]]
+    "
+}
+
+#[test]
+fn format_string_error_position() {
+    let code = "#[no_std]\nx = f\"ab {1 + *} c\"\n";
+    let err = match crate::parse_spwn(
+        code.to_string(),
+        shared::SpwnSource::String(internment::LocalIntern::new(code.to_string())),
+        crate::builtins::BUILTIN_NAMES,
+    ) {
+        Err(e) => e,
+        Ok(_) => panic!("the f-string should not parse"),
+    };
+    // errors inside the braces point at the text in the file
+    let at = code.find('*').unwrap();
+    match err {
+        errors::SyntaxError::ExpectedErr { pos, .. }
+        | errors::SyntaxError::UnexpectedErr { pos, .. }
+        | errors::SyntaxError::SyntaxError { pos, .. } => assert_eq!(pos, (at, at + 1)),
+        errors::SyntaxError::CustomError(_) => panic!("unexpected error kind"),
+    }
+}
+
+// enums
+run_test! {
+    NAME: enums
+    CODE: r"
+#[no_std]
+type @shape { circle(radius), rect(w, h), empty }
+area = (s: @shape) => match s {
+    @shape::circle(r): 3 * r * r,
+    @shape::rect(w, _): w * 10,
+    @shape::empty: 0,
+}
+$.print(area(@shape::circle(2)), ' ', area(@shape::rect(2, 5)), ' ', area(@shape::empty))
+$.print(@shape::rect(1, 2).h)
+try {
+    match @shape::empty {
+        @shape::circle(r): r,
+        @shape::empty: 0,
+    }
+} catch e {
+    $.print(e.message)
+}
+    "
+    OUTPUT: r"
+12 20 0
+2
+this match doesn't handle every variant of @shape
+    "
+}
+
+// interfaces
+run_test! {
+    NAME: interfaces
+    CODE: r"
+#[no_std]
+interface @drawable { draw }
+type @circle
+impl @circle: @drawable { draw: (self) => 'circle' }
+type @square
+render = (d: @drawable) => d.draw()
+$.print(render(@circle::{}), ' ', @square::{} is @drawable)
+impl @square { draw: (self) => 'square' }
+$.print(render(@square::{}))
+type @line
+try {
+    impl @line: @drawable { length: (self) => 1 }
+} catch e {
+    $.print(e.message)
+}
+// the rejected impl doesn't add its members
+try {
+    $.print(@line::{}.length())
+} catch e {
+    $.print('no length')
+}
+    "
+    OUTPUT: r"
+circle false
+square
+@line doesn't implement every member of @drawable
+no length
+    "
+}
+
+// destructuring
+run_test! {
+    NAME: destructuring
+    CODE: r"
+#[no_std]
+[a, b, ..rest] = [1, 2]
+{x, y: py} = {x: 3, y: 4}
+$.print(a, b, rest, x, py)
+for [i, {v}] in [[0, {v: 'a'}], [1, {v: 'b'}]] {
+    $.print(i, v)
+}
+m = ([first, ..others], {name}) => name + ': ' + $.display(first)
+$.print(m([5, 6], {name: 'n'}))
+$.print(m)
+try {
+    [c, d] = [1, 2, 3]
+} catch e {
+    $.print(e.kind)
+}
+try {
+    m([5])
+} catch e {
+    $.print(e.message)
+}
+    "
+    OUTPUT: r"
+12[]34
+0a
+1b
+n: 5
+(_, _) { /* ... */ }
+pattern_mismatch
+Non-optional argument '_' not satisfied!
+    "
+}
+
+// pattern variables
+run_test! {
+    NAME: pattern_variables
+    CODE: r"
+#[no_std]
+$.print([1, 2] is [?T], ' ', [1, 'a'] is [?T])
+$.print([[1], ['a']] is [?T], ' ', [[], [1], [2]] is [?T], ' ', [[1], []] is [?T])
+first = (arr: [?T], default: ?T) -> ?T => arr[0] if arr.length > 0 else default
+apply = (x: ?T, f: (?T) -> ?U) => f(x)
+$.print(first([4, 5], 0), ' ', first([], 'none'), ' ', apply(3, (n: @number) => n * 2))
+try {
+    first([1, 2], 'a')
+} catch e {
+    $.print(e.kind)
+}
+try {
+    apply('a', (n: @number) => n * 2)
+} catch e {
+    $.print(e.kind)
+}
+// ?T is bound to @string by the first branch, which then doesn't match
+pick = (x: (?T & @bool) | @string, y: ?T) => y
+$.print(pick('a', 5))
+    "
+    OUTPUT: r"
+true false
+false true true
+4 none 6
+pattern_mismatch
+pattern_mismatch
+5
+    "
+}
+
+#[test]
+fn pattern_variable_mismatch() {
+    use crate::compiler::{compile_spwn, CompileOptions};
+    use crate::ModuleCache;
+    use errors::RuntimeError;
+    use shared::SpwnSource;
+
+    let code = r"
+#[no_std]
+first = (arr: [?T], default: ?T) -> ?T => arr[0] if arr.length > 0 else default
+first([1, 2], 'a')
+";
+    let source = SpwnSource::String(internment::LocalIntern::new(code.to_string()));
+    let (statements, notes) =
+        crate::parse_spwn(code.to_string(), source.clone(), crate::builtins::BUILTIN_NAMES)
+            .unwrap();
+    let mut std_out = Vec::<u8>::new();
+    let mut module_cache = ModuleCache::default();
+    let result = compile_spwn(
+        statements,
+        source,
+        vec![PathBuf::from("./")],
+        notes,
+        Default::default(),
+        String::new(),
+        &mut std_out,
+        &mut module_cache,
+        CompileOptions {
+            quiet: true,
+            ..Default::default()
+        },
+    );
+    match result {
+        Err(RuntimeError::PatternMismatchError { pattern, .. }) => {
+            assert_eq!(pattern, "?T (where ?T = @number)")
+        }
+        _ => panic!("expected a pattern mismatch"),
+    }
+}
+
+// runtime arrays
+run_test! {
+    NAME: runtime_arrays
+    CODE: r"
+arr = @runtime_array::new(5)
+i = counter(3)
+arr.set(i, 7)
+arr.set(0, counter(2))
+$.print(arr.get(i))
+$.print(arr)
+    "
+    OUTPUT: r"
+counter(?i, bits = 16)
+runtime_array(length = 5, triggers = 4746)
+    "
+}
+
+// the search trees of a runtime array, run with a counter as the index
+#[test]
+fn runtime_array_triggers() {
+    let code = r"
+arr = @runtime_array::new(5)
+[i, two, four, nine, zero, one] = [counter(3), counter(2), counter(4), counter(9), counter(0), counter(1)]
+arr.set(i, 7)
+arr.set(0, two)
+arr.set(four, nine)
+// the read leaf has to copy the element before get adds the value to its result
+x = arr.get(i)
+y = arr.get(zero)
+z = arr.get(one)
+arr.set(i, 1)
+w = arr.get(i)
+outputs = [x, y, z, w, i, arr.index, arr.value] + arr.items
+for n in 0..outputs.length {
+    $.item_transfer(outputs[n].item, [[@item(100 + n), 1]], 16, 1, true)
+}
+";
+    let [_, level] = run_spwn(code.to_string(), vec![PathBuf::from("./")], false).unwrap();
+    let items = run_triggers(&level);
+    let values = (100..112)
+        .map(|i| items.get(&i).copied().unwrap_or(0))
+        .collect::<Vec<_>>();
+    // x, y, z and w, then the index (which isn't consumed), the search's index and value
+    // (which are left at 0) and the elements
+    assert_eq!(values, vec![7, 2, 0, 1, 3, 0, 0, 2, 0, 0, 1, 9]);
+}
+
+#[test]
+fn sync_groups() {
+    let code = r"
+#[no_std]
+sync a
+f = !{
+    a[1] {
+        $.add(trigger {1: 1268, 51: ?g})
+        $.add(trigger {1: 1268, 51: ?g})
+    }
+}
+g = !{
+    a[2] {
+        $.add(trigger {1: 1268, 51: ?g})
+    }
+}
+try {
+    a[1] {}
+    a[2] {}
+} catch e {
+    $.print(e.message)
+}
+";
+    let [output, level] = run_spwn(code.to_string(), vec![PathBuf::from("./")], false).unwrap();
+    assert_eq!(
+        output.trim(),
+        "parts 1 and 2 of the sync group 'a' run at the same time"
+    );
+
+    // part 2 reuses one of the groups made in part 1
+    let mut targets = level
+        .split(';')
+        .filter_map(|obj| {
+            let props = obj.split(',').collect::<Vec<_>>();
+            props.chunks(2).find(|p| p[0] == "51").map(|p| p[1].to_string())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(targets.len(), 3);
+    targets.sort();
+    targets.dedup();
+    assert_eq!(targets.len(), 2);
+
+    // parts in trigger functions that are started from the same context also run at the same time
+    let parts = r"
+#[no_std]
+sync a
+f = !{
+    a[1] {}
+}
+g = !{
+    a[2] {}
+}
+";
+    let build = |calls: &str| {
+        run_spwn(format!("{}{}", parts, calls), vec![PathBuf::from("./")], false)
+            .map(|_| ())
+            .map_err(|e| e.contains("parts 1 and 2 of the sync group 'a' run at the same time"))
+    };
+    assert_eq!(build("f!\ng!"), Err(true));
+    assert_eq!(build("h = !{\n    g!\n}\nf!\nh!"), Err(true));
+    // g is only started a second after f
+    assert_eq!(
+        build("h = !{\n    g!\n}\nf!\n$.add(trigger {1: 1268, 51: h, 63: 1})"),
+        Ok(())
+    );
+    // the parts entered in a failed try block don't count
+    assert_eq!(
+        build("try {\n    a[1] {}\n    a[2] {}\n} catch {}\ng!"),
+        Ok(())
+    );
+}
+
+// modules with exports (the library is in tests/libraries)
+run_test! {
+    NAME: exports
+    INCLUDE: "tests"
+    CODE: r"
+#[no_std]
+lib = import exports_lib
+$.print(lib.double(lib.count), ' ', 'helper' in lib)
+    "
+    OUTPUT: r"
+6 false
+    "
+}
+
+// context limit
+#[test]
+fn context_limit() {
+    use crate::compiler::{compile_spwn, CompileOptions};
+    use crate::ModuleCache;
+    use errors::RuntimeError;
+    use shared::SpwnSource;
+
+    // every call returns in two contexts that can't be merged
+    let code = r"
+#[no_std]
+split = () {
+    f = !{
+        -> return 1
+    }
+    f!
+    return 2
+}
+a1 = split()
+a2 = split()
+a3 = split()
+a4 = split()
+a5 = split()
+";
+    let source = SpwnSource::String(internment::LocalIntern::new(code.to_string()));
+    let (statements, notes) =
+        crate::parse_spwn(code.to_string(), source.clone(), crate::builtins::BUILTIN_NAMES)
+            .unwrap();
+    let mut std_out = Vec::<u8>::new();
+    let mut module_cache = ModuleCache::default();
+    let result = compile_spwn(
+        statements,
+        source,
+        vec![PathBuf::from("./")],
+        notes,
+        Default::default(),
+        String::new(),
+        &mut std_out,
+        &mut module_cache,
+        CompileOptions {
+            max_contexts: Some(10),
+            quiet: true,
+            ..Default::default()
+        },
+    );
+    match result {
+        Err(RuntimeError::ContextLimitError {
+            limit,
+            count,
+            splits,
+            ..
+        }) => {
+            assert_eq!((limit, count), (10, 16));
+            let lines = splits
+                .iter()
+                .map(|s| code[..s.pos.0].lines().count())
+                .collect::<Vec<_>>();
+            // the return in the trigger function and the macro call, for the first 4 calls
+            assert_eq!(lines, [5, 10, 5, 11, 5, 12, 5, 13]);
+        }
+        Err(e) => panic!(
+            "expected a context limit error, got {}",
+            errors::ErrorReport::from(e).message
+        ),
+        Ok(_) => panic!("expected a context limit error"),
+    }
+}
+
+// reusing modules between builds
+#[test]
+fn module_cache() {
+    use crate::compiler::{compile_spwn, CompileOptions};
+    use crate::ModuleCache;
+    use shared::SpwnSource;
+
+    let dir = std::env::temp_dir().join("spwn_module_cache");
+    std::fs::create_dir_all(&dir).unwrap();
+    let lib = dir.join("lib.spwn");
+    let main = dir.join("main.spwn");
+
+    // returns what was printed, the groups the triggers target and how many modules were reused
+    let build = |code: &str, cache: &mut ModuleCache| {
+        std::fs::write(&main, code).unwrap();
+        let source = SpwnSource::File(main.clone());
+        let (statements, notes) =
+            crate::parse_spwn(code.to_string(), source.clone(), crate::builtins::BUILTIN_NAMES)
+                .unwrap();
+        let mut std_out = Vec::<u8>::new();
+        let mut targets = {
+            let compiled = compile_spwn(
+                statements,
+                source,
+                vec![dir.clone()],
+                notes,
+                Default::default(),
+                String::new(),
+                &mut std_out,
+                cache,
+                CompileOptions {
+                    quiet: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            compiled
+                .func_ids
+                .iter()
+                .flat_map(|f| f.obj_list.iter())
+                .map(|(obj, _)| format!("{:?}", obj.params[&51]))
+                .collect::<Vec<_>>()
+        };
+        targets.sort();
+        let printed = String::from_utf8(std_out).unwrap();
+        (printed.trim().to_string(), targets, cache.reused)
+    };
+
+    std::fs::write(
+        &lib,
+        r"
+#[no_std]
+type @thing
+g = ?g
+$.add(trigger {1: 1268, 51: g})
+return { g, make: () => @thing::{} }
+",
+    )
+    .unwrap();
+
+    let mut cache = ModuleCache::default();
+    let main_code = r"
+#[no_std]
+a = ?g
+$.add(trigger {1: 1268, 51: a})
+l = import 'lib.spwn'
+$.print(l.g == a, ' ', l.make().type)
+";
+    let first = build(main_code, &mut cache);
+    assert_eq!(first.0, "false @thing");
+    assert_eq!(first.2, 0);
+    assert_eq!(build(main_code, &mut cache), (first.0.clone(), first.1.clone(), 1));
+
+    // the main script takes another group first, so the module's group has to move up
+    let (printed, targets, reused) = build(
+        r"
+#[no_std]
+a = ?g
+b = ?g
+$.add(trigger {1: 1268, 51: a})
+$.add(trigger {1: 1268, 51: b})
+l = import 'lib.spwn'
+$.print(l.g == a || l.g == b, ' ', l.make().type)
+",
+        &mut cache,
+    );
+    assert_eq!((printed.as_str(), reused), ("false @thing", 1));
+    let mut unique = targets.clone();
+    unique.dedup();
+    assert_eq!(unique.len(), 3);
+
+    // changing the module builds it again
+    std::fs::write(
+        &lib,
+        r"
+#[no_std]
+type @thing
+g = ?g
+return { g, make: () => 'changed' }
+",
+    )
+    .unwrap();
+    let (printed, targets, reused) = build(main_code, &mut cache);
+    assert_eq!((printed.as_str(), reused), ("false @string", 0));
+    assert_eq!(targets.len(), 1);
+
+    // a module that prints is reused, and what it printed is printed again
+    std::fs::write(&lib, "#[no_std]\n$.print('lib')\nreturn {g: ?g}").unwrap();
+    let main_code = "#[no_std]\nl = import 'lib.spwn'\n$.print(l.g)";
+    let first = build(main_code, &mut cache);
+    assert_eq!((first.0.as_str(), first.2), ("lib\n?g", 0));
+    assert_eq!(build(main_code, &mut cache), (first.0, first.1, 1));
+    // but not when its ids moved, since ids can be printed with their numbers
+    let (printed, _, reused) = build("#[no_std]\na = ?g\nimport 'lib.spwn'", &mut cache);
+    assert_eq!((printed.as_str(), reused), ("lib", 0));
+}
+
+// the module cache with several output levels
+#[test]
+fn module_cache_output_levels() {
+    use crate::compiler::{compile_spwn, CompileOptions};
+    use crate::ModuleCache;
+    use shared::SpwnSource;
+
+    let dir = std::env::temp_dir().join("spwn_module_cache_levels");
+    std::fs::create_dir_all(&dir).unwrap();
+    let main = dir.join("main.spwn");
+    std::fs::write(
+        dir.join("lib.spwn"),
+        "#[no_std]\n$.add(trigger {1: 1268, 51: 5g})\nreturn {}",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("switch.spwn"),
+        "#[no_std]\n$.output_level('b')\n$.add(trigger {1: 1268, 51: 6g})\nreturn {}",
+    )
+    .unwrap();
+
+    // returns the output levels, the level every trigger went in and how many modules were reused
+    let build = |code: &str, cache: &mut ModuleCache| {
+        std::fs::write(&main, code).unwrap();
+        let source = SpwnSource::File(main.clone());
+        let (statements, notes) =
+            crate::parse_spwn(code.to_string(), source.clone(), crate::builtins::BUILTIN_NAMES)
+                .unwrap();
+        let mut std_out = Vec::<u8>::new();
+        let compiled = compile_spwn(
+            statements,
+            source,
+            vec![dir.clone()],
+            notes,
+            Default::default(),
+            String::new(),
+            &mut std_out,
+            cache,
+            CompileOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let mut triggers = compiled
+            .func_ids
+            .iter()
+            .flat_map(|f| f.obj_list.iter())
+            .map(|(obj, _)| (format!("{}", obj.params[&51]), obj.output))
+            .collect::<Vec<_>>();
+        triggers.sort();
+        (compiled.output_levels.clone(), triggers, cache.reused)
+    };
+
+    let mut cache = ModuleCache::default();
+    let main_code = r"
+#[no_std]
+$.output_level('a')
+import 'lib.spwn'
+import 'switch.spwn'
+$.add(trigger {1: 1268, 51: 7g})
+";
+    let first = build(main_code, &mut cache);
+    assert_eq!(first.0, ["a", "b"]);
+    // the module's $.output_level doesn't change the level of the script that imports it
+    assert_eq!(
+        first.1,
+        [("5".to_string(), 1), ("6".to_string(), 2), ("7".to_string(), 1)]
+    );
+    assert_eq!(first.2, 0);
+    // only the module that didn't switch levels is reused
+    assert_eq!(build(main_code, &mut cache), (first.0, first.1, 1));
+
+    // the levels are matched by name, so the module is reused when its level has another index
+    let (levels, triggers, reused) = build(
+        r"
+#[no_std]
+$.output_level('c')
+$.output_level('a')
+import 'lib.spwn'
+",
+        &mut cache,
+    );
+    assert_eq!(levels, ["c", "a"]);
+    assert_eq!(triggers, [("5".to_string(), 2)]);
+    assert_eq!(reused, 1);
+}
+
+// compile time profile
+#[test]
+fn profiler() {
+    use ::compiler::profiler::Profiler;
+
+    let mut profiler = Profiler::default();
+    profiler.enter("main.spwn".to_string());
+    for _ in 0..2 {
+        profiler.enter("macro a;b".to_string());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        profiler.exit();
+    }
+    profiler.exit();
+
+    let folded = profiler.folded();
+    let lines = folded.lines().collect::<Vec<_>>();
+    // the ; in the name would start a new frame
+    assert!(lines.iter().any(|l| l.starts_with("main.spwn;macro a:b ")));
+    let time = |l: &str| l.rsplit(' ').next().unwrap().parse::<u64>().unwrap();
+    assert!(lines.iter().all(|l| time(l) > 0));
+
+    let summary = profiler.summary(10);
+    let first = summary.lines().next().unwrap();
+    // the time of the macro calls counts for the script too
+    assert!(first.ends_with("main.spwn"), "{}", summary);
+    assert!(summary
+        .lines()
+        .any(|l| l.contains("2 calls") && l.ends_with("macro a:b")));
+}
+
+// path scoped file permissions
+#[test]
+fn scoped_file_permissions() {
+    use crate::builtins::{Builtin, BuiltinPermissions, FileAccess};
+
+    let mut permissions = BuiltinPermissions::new();
+    permissions
+        .allow_path(FileAccess::Write, "target/sandbox".as_ref())
+        .unwrap();
+
+    assert!(permissions.is_allowed(Builtin::WriteFile));
+    assert!(!permissions.is_allowed(Builtin::ReadFile));
+    assert!(permissions
+        .check_path(Builtin::WriteFile, "target/sandbox/new/out.json")
+        .is_ok());
+    assert!(permissions
+        .check_path(Builtin::WriteFile, "target/sandbox/../secret.txt")
+        .is_err());
+    assert!(permissions
+        .check_path(Builtin::DeleteFile, "target/sandbox2/out.json")
+        .is_err());
+}
+
+// per package permissions
+#[test]
+fn package_permissions() {
+    use crate::builtins::{Builtin, BuiltinPermissions};
+
+    let mut permissions = BuiltinPermissions::new();
+    permissions.set(Builtin::HTTPRequest, true);
+    permissions.grant_package("netlib".to_string(), vec![Builtin::ReadFile]);
+
+    let chain = |modules: &[&str]| modules.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+
+    assert!(permissions.is_allowed_in(Builtin::HTTPRequest, &chain(&[])));
+    assert!(!permissions.is_allowed_in(Builtin::ReadFile, &chain(&[])));
+    assert!(!permissions.is_allowed_in(Builtin::HTTPRequest, &chain(&["netlib"])));
+    assert!(permissions.is_allowed_in(Builtin::ReadFile, &chain(&["netlib"])));
+    assert!(permissions.is_allowed_in(Builtin::Print, &chain(&["netlib"])));
+    // libraries that aren't pckp packages work like before
+    assert!(permissions.is_allowed_in(Builtin::HTTPRequest, &chain(&["other"])));
+    // but calling them from a package doesn't get around its permissions
+    assert!(!permissions.is_allowed_in(Builtin::HTTPRequest, &chain(&["netlib", "std"])));
+    assert!(permissions.is_allowed_in(Builtin::ReadFile, &chain(&["netlib", "std"])));
+
+    // every package in the chain needs the builtin
+    permissions.grant_package("filelib".to_string(), vec![Builtin::ReadFile, Builtin::HTTPRequest]);
+    assert!(permissions.is_allowed_in(Builtin::HTTPRequest, &chain(&["filelib"])));
+    assert!(!permissions.is_allowed_in(Builtin::HTTPRequest, &chain(&["netlib", "filelib"])));
+    assert_eq!(
+        permissions.package_without(Builtin::HTTPRequest, &chain(&["filelib", "std", "netlib"])),
+        Some(&"netlib".to_string())
+    );
+}
+
+// a package calling a builtin through the standard library
+#[test]
+fn package_permissions_through_std() {
+    use crate::builtins::{Builtin, BuiltinPermissions};
+    use crate::compiler::{compile_spwn, CompileOptions};
+    use crate::ModuleCache;
+    use ::compiler::http_fixtures::{HttpFixtures, HttpResponse};
+    use errors::RuntimeError;
+    use shared::SpwnSource;
+
+    let dir = std::env::temp_dir().join("spwn_package_permissions");
+    let lib = dir.join("libraries").join("netlib");
+    std::fs::create_dir_all(&lib).unwrap();
+    std::fs::write(
+        lib.join("lib.spwn"),
+        "return { fetch: (url) => @http::get(url).text }",
+    )
+    .unwrap();
+    let fixtures = HttpFixtures {
+        dir: dir.join("fixtures"),
+        record: true,
+    };
+    fixtures
+        .save(&HttpResponse {
+            method: "get".to_string(),
+            url: "https://example.com".to_string(),
+            status: 200,
+            headers: Default::default(),
+            text: "hello".to_string(),
+        })
+        .unwrap();
+
+    let build = |granted: Vec<Builtin>| {
+        let code = "netlib = import netlib\n$.print(netlib.fetch('https://example.com'))";
+        let source = SpwnSource::String(internment::LocalIntern::new(code.to_string()));
+        let (statements, notes) =
+            crate::parse_spwn(code.to_string(), source.clone(), crate::builtins::BUILTIN_NAMES)
+                .unwrap();
+        // the script itself may make requests
+        let mut permissions = BuiltinPermissions::new();
+        permissions.set(Builtin::HTTPRequest, true);
+        permissions.grant_package("netlib".to_string(), granted);
+        let mut std_out = Vec::<u8>::new();
+        compile_spwn(
+            statements,
+            source,
+            vec![dir.clone()],
+            notes,
+            permissions,
+            String::new(),
+            &mut std_out,
+            &mut ModuleCache::default(),
+            CompileOptions {
+                quiet: true,
+                http_fixtures: Some(HttpFixtures {
+                    dir: dir.join("fixtures"),
+                    record: false,
+                }),
+                ..Default::default()
+            },
+        )
+        // the builtin and message of the error
+        .map_err(|e| match e {
+            RuntimeError::BuiltinError { builtin, message, .. } => (builtin, message),
+            e => (String::new(), format!("{:?}", e)),
+        })?;
+        Ok(String::from_utf8_lossy(&std_out).to_string())
+    };
+
+    let (builtin, message) = build(Vec::new()).unwrap_err();
+    assert_eq!(builtin, "http_request");
+    assert!(
+        message.starts_with("The package `netlib` was not given permission"),
+        "{}",
+        message
+    );
+    assert_eq!(build(vec![Builtin::HTTPRequest]).unwrap().trim(), "hello");
+}
+
+// recorded http responses
+#[test]
+fn http_fixtures() {
+    use ::compiler::http_fixtures::{HttpFixtures, HttpResponse};
+
+    let fixtures = HttpFixtures {
+        dir: PathBuf::from("target/http_fixtures_test"),
+        record: true,
+    };
+    let response = HttpResponse {
+        method: "get".to_string(),
+        url: "https://example.com/api?page=1".to_string(),
+        status: 200,
+        headers: [("content-type".to_string(), "text/plain".to_string())].into(),
+        text: "hello".to_string(),
+    };
+    fixtures.save(&response).unwrap();
+
+    assert_eq!(fixtures.load("get", "https://example.com/api?page=1"), Ok(response));
+    assert!(fixtures.load("get", "https://example.com/api?page=2").is_err());
+    assert!(fixtures.load("post", "https://example.com/api?page=1").is_err());
+}
+
+// -D values
+#[test]
+fn define_values() {
+    use crate::compiler::parse_define;
+    use crate::value::Value;
+
+    assert_eq!(parse_define("true"), Value::Bool(true));
+    assert_eq!(parse_define("-2.5"), Value::Number(-2.5));
+    assert_eq!(parse_define("hard"), Value::Str("hard".to_string()));
+    assert_eq!(parse_define("\"10\""), Value::Str("10".to_string()));
+    // only finite numbers
+    assert_eq!(parse_define("inf"), Value::Str("inf".to_string()));
+    assert_eq!(parse_define("-infinity"), Value::Str("-infinity".to_string()));
+    assert_eq!(parse_define("NaN"), Value::Str("NaN".to_string()));
+}
+
+// spwn test
+#[test]
+fn test_runner() {
+    use crate::builtins::BuiltinPermissions;
+    use crate::test_runner::{find_tests, run_test, TestOutcome};
+
+    let code = r#"
+#[no_std]
+double = (n) => n * 2
+
+passes = #[test] () {
+    $.assert(double(2) == 4)
+}
+fails = #[test] () {
+    $.assert(double(2) == 5)
+}
+prints = #[test, expect_output("4")] () {
+    $.print(double(2))
+}
+errors = #[test, expect_error("Assertion failed")] () {
+    $.assert(false)
+}
+not_a_test = () {}
+    "#;
+    let (statements, _) = crate::parse_spwn(
+        code.to_string(),
+        shared::SpwnSource::String(internment::LocalIntern::new(code.to_string())),
+        crate::builtins::BUILTIN_NAMES,
+    )
+    .unwrap();
+    let tests = find_tests(&statements);
+    assert_eq!(
+        tests.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+        ["passes", "fails", "prints", "errors"]
+    );
+
+    let path = std::env::temp_dir().join("spwn_test_runner.spwn");
+    let passed = tests
+        .iter()
+        .map(|t| {
+            run_test(&path, code, t, Vec::new(), BuiltinPermissions::new()) == TestOutcome::Passed
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(passed, [true, false, true, true]);
+}
+
+// runs the pickup, instant count, spawn and toggle triggers in a level string,
+// starting from the top level ones, and returns the item values after. an instant
+// count trigger runs its group right away, and a spawn trigger runs it after its
+// delay, once the groups that were started before it are done
+fn run_triggers(level: &str) -> std::collections::HashMap<i64, i64> {
+    use std::cmp::Reverse;
+    use std::collections::{BinaryHeap, HashMap, HashSet};
+
+    let objects = level
+        .split(';')
+        .filter(|obj| !obj.is_empty())
+        .map(|obj| {
+            let props = obj.split(',').collect::<Vec<_>>();
+            props
+                .chunks(2)
+                .map(|p| (p[0].parse::<u16>().unwrap(), p[1].to_string()))
+                .collect::<HashMap<_, _>>()
+        })
+        .collect::<Vec<_>>();
+    let num = |obj: &HashMap<u16, String>, key| obj[&key].parse::<f64>().unwrap() as i64;
+
+    // (time in milliseconds, order of spawning, group)
+    type Spawned = BinaryHeap<Reverse<(u64, usize, i64)>>;
+
+    struct State {
+        items: HashMap<i64, i64>,
+        disabled: HashSet<i64>,
+        spawned: Spawned,
+        spawn_count: usize,
+        time: u64,
+    }
+
+    fn run(
+        group: i64,
+        objects: &[HashMap<u16, String>],
+        state: &mut State,
+        num: &dyn Fn(&HashMap<u16, String>, u16) -> i64,
+    ) {
+        if state.disabled.contains(&group) {
+            return;
+        }
+        for obj in objects {
+            let in_group = obj
+                .get(&57)
+                .map(|g| g.split('.').any(|g| g.parse::<i64>().unwrap() == group))
+                .unwrap_or(group == 0);
+            if !in_group {
+                continue;
+            }
+            match num(obj, 1) {
+                1817 => *state.items.entry(num(obj, 80)).or_default() += num(obj, 77),
+                1811 => {
+                    let value = state.items.get(&num(obj, 80)).copied().unwrap_or(0);
+                    let count = num(obj, 77);
+                    let passed = match num(obj, 88) {
+                        0 => value == count,
+                        1 => value > count,
+                        _ => value < count,
+                    };
+                    if passed {
+                        run(num(obj, 51), objects, state, num);
+                    }
+                }
+                1268 => {
+                    let delay = obj.get(&63).map(|d| d.parse::<f64>().unwrap()).unwrap_or(0.0);
+                    let time = state.time + (delay * 1000.0).round() as u64;
+                    state.spawn_count += 1;
+                    state
+                        .spawned
+                        .push(Reverse((time, state.spawn_count, num(obj, 51))));
+                }
+                1049 => {
+                    if obj.get(&56).map(|a| a == "1").unwrap_or(false) {
+                        state.disabled.remove(&num(obj, 51));
+                    } else {
+                        state.disabled.insert(num(obj, 51));
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let mut state = State {
+        items: HashMap::new(),
+        disabled: HashSet::new(),
+        spawned: Spawned::new(),
+        spawn_count: 0,
+        time: 0,
+    };
+    state.spawned.push(Reverse((0, 0, 0)));
+    while let Some(Reverse((time, _, group))) = state.spawned.pop() {
+        assert!(state.spawn_count < 100_000, "the triggers keep spawning each other");
+        state.time = time;
+        run(group, &objects, &mut state, &num);
+    }
+    state.items
+}
+
+#[test]
+fn fixed_point_triggers() {
+    let code = r"
+a = @fixed::new(1.5)
+a *= -3
+b = @fixed::new(-7.25)
+b /= 2
+c = @fixed::new(-1.5)
+c *= @fixed::new(2.25)
+d = @fixed::new(4.5)
+d /= @fixed::new(-1.5)
+e = @fixed::new(0.1, scale = 1000)
+e -= 0.35
+e *= e
+for [i, f] in [[100, a], [101, b], [102, c], [103, d], [104, e]] {
+    $.item_transfer(f.raw.item, [[@item(i), 1]], 16, 1, true)
+}
+";
+    let [_, level] = run_spwn(code.to_string(), vec![PathBuf::from("./")], false).unwrap();
+    let items = run_triggers(&level);
+    let values = (100..105).map(|i| items[&i]).collect::<Vec<_>>();
+    // -4.5, -3.62 (rounded towards 0), -3.37, -3 and 0.0625
+    assert_eq!(values, vec![-450, -362, -337, -300, 62]);
+}
@@ -0,0 +1,271 @@
+// runs the spwn binary on small scripts, for the build options that only exist on the command line
+
+use std::process::{Command, Output};
+
+// writes the script to a file of its own and builds it with the given arguments
+fn build(name: &str, code: &str, args: &[&str]) -> Output {
+    let dir = std::env::temp_dir().join("spwn_cli").join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("main.spwn");
+    std::fs::write(&script, code).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_spwn"))
+        .arg("build")
+        .arg(&script)
+        .args(args)
+        .current_dir(&dir)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+// --emit triggers
+#[test]
+fn emit_triggers() {
+    let output = build(
+        "emit_triggers",
+        r"
+#[no_std]
+$.add(trigger {1: 1268, 51: 10g})
+f = !{
+    $.add(trigger {1: 1268, 51: 11g})
+}
+f!
+",
+        &["--emit", "triggers", "--no-optimize"],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    assert!(out.contains("function 0 (top level):"), "{}", out);
+    assert!(out.contains("function 1 (parent: function 0):"), "{}", out);
+    assert!(out.contains("1: 1268, 51: 11"), "{}", out);
+    // no level is written
+    assert!(!out.contains("Output:"), "{}", out);
+
+    let output = build("emit_no_format", "#[no_std]", &["--emit"]);
+    assert!(!output.status.success());
+}
+
+// --profile
+#[test]
+fn profile() {
+    let output = build(
+        "profile",
+        r"
+#[no_std]
+slow = (n) {
+    let total = 0
+    for i in 0..n {
+        total += i
+    }
+    return total
+}
+for i in 0..20 {
+    slow(2000)
+}
+",
+        &["--profile", "build.folded", "--no-level"],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Slowest parts of the build:"));
+
+    let dir = std::env::temp_dir().join("spwn_cli").join("profile");
+    let folded = std::fs::read_to_string(dir.join("build.folded")).unwrap();
+    let script = dir.join("main.spwn").display().to_string();
+    // the macro is called from the top of the script, and is defined on line 3
+    let line = folded
+        .lines()
+        .find(|l| l.starts_with(&format!("{};macro {}:3 ", script, script)))
+        .unwrap_or_else(|| panic!("no line for the macro in:\n{}", folded));
+    assert!(line.rsplit(' ').next().unwrap().parse::<u64>().unwrap() > 0);
+
+    let output = build("profile_no_file", "#[no_std]", &["--profile"]);
+    assert!(!output.status.success());
+}
+
+// --trace-contexts and --max-contexts
+#[test]
+fn trace_contexts() {
+    let code = r"
+#[no_std]
+split = () {
+    f = !{
+        -> return 1
+    }
+    f!
+    return 2
+}
+a1 = split()
+a2 = split()
+a3 = split()
+";
+    let output = build("trace_contexts", code, &["--no-level", "--trace-contexts"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let trace = stdout(&output)
+        .lines()
+        .filter(|l| l.starts_with("[contexts]"))
+        .map(|l| l.rsplit_once(" at ").unwrap())
+        .map(|(message, at)| format!("{} {}", message, at.rsplit_once("main.spwn:").unwrap().1))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        trace[..3],
+        [
+            "[contexts] statement made 1 new context 5:9",
+            "[contexts] split into 2 contexts by returning from inside a trigger function 4:5",
+            "[contexts] macro returned in 2 contexts 10:6",
+        ]
+    );
+    // 2, 4 and then 8 contexts at the end
+    assert_eq!(
+        trace
+            .iter()
+            .filter(|l| l.contains("macro returned"))
+            .count(),
+        1 + 2 + 4
+    );
+
+    let output = build("max_contexts", code, &["--no-level", "--max-contexts", "5"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Too many contexts (8 at once, the limit is 5)"));
+}
+
+// --seed
+#[test]
+fn seed() {
+    let code = r"
+#[no_std]
+$.print($.random(1..1000000))
+";
+    let printed = |output: Output| {
+        assert!(output.status.success(), "{}", stderr(&output));
+        stdout(&output)
+            .lines()
+            .find(|l| l.parse::<u64>().is_ok())
+            .unwrap()
+            .to_string()
+    };
+    let first = printed(build("seed", code, &["--no-level", "--seed", "7"]));
+    assert_eq!(
+        first,
+        printed(build("seed", code, &["--no-level", "--seed", "7"]))
+    );
+    assert_ne!(
+        first,
+        printed(build("seed", code, &["--no-level", "--seed", "8"]))
+    );
+
+    let output = build("seed_no_value", code, &["--no-level", "--seed"]);
+    assert!(!output.status.success());
+}
+
+// --input and --inputs
+#[test]
+fn inputs() {
+    let code = r"
+#[no_std]
+$.print($.get_input('Name? ', 'name'))
+$.print($.get_input('Level: '))
+";
+    let printed = |output: Output| {
+        assert!(output.status.success(), "{}", stderr(&output));
+        stdout(&output)
+            .lines()
+            .filter(|l| l.starts_with("in:"))
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>()
+    };
+    // the key, or the prompt without the spaces around it
+    let output = build(
+        "input",
+        code,
+        &[
+            "--no-level",
+            "--input",
+            "name=in: a=b",
+            "--input",
+            "Level:=in: 3",
+        ],
+    );
+    assert_eq!(printed(output), ["in: a=b", "in: 3"]);
+
+    let dir = std::env::temp_dir().join("spwn_cli").join("inputs");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("answers.txt"), "name = in: file\nLevel: = in: 1\n").unwrap();
+    let output = build(
+        "inputs",
+        code,
+        &[
+            "--no-level",
+            "--inputs",
+            "answers.txt",
+            "--input",
+            "Level:=in: 2",
+        ],
+    );
+    // --input wins over the file
+    assert_eq!(printed(output), ["in: file", "in: 2"]);
+
+    // stdin isn't a terminal here, so a missing input can't be asked for
+    let output = build("input_missing", code, &["--no-level", "--input", "name=x"]);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("No input was given for \"Level:\""),
+        "{}",
+        stderr(&output)
+    );
+}
+
+// -D
+#[test]
+fn defines() {
+    let code = r"
+for name in ['count', 'hard', 'name', 'quoted', 'big'] {
+    value = $.defines[name]
+    $.print('define {}: {} {}'.fmt([name, value, value.type]))
+}
+$.print('define missing: {}'.fmt(('missing' in $.defines) as @string))
+";
+    let output = build(
+        "defines",
+        code,
+        &[
+            "--no-level",
+            "-D",
+            "count=3",
+            "-D",
+            "hard=true",
+            "-D",
+            "name=inf",
+            "-D",
+            "quoted=\"2\"",
+            "-D",
+            "big=1",
+            "-D",
+            "big=2.5",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let printed = stdout(&output)
+        .lines()
+        .filter(|l| l.starts_with("define "))
+        .map(|l| l.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        printed,
+        [
+            "define count: 3 @number",
+            "define hard: true @bool",
+            "define name: inf @string",
+            "define quoted: 2 @string",
+            // the last -D wins
+            "define big: 2.5 @number",
+            "define missing: false",
+        ]
+    );
+}
@@ -0,0 +1,279 @@
+// the level (and printed output) of every script in test/, compared with the files in
+// test/snapshots. run with SPWN_BLESS=1 to write them again after a change that is supposed
+// to change the output.
+// this is its own test binary because some scripts are built from another working directory
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use compiler::builtins::{Builtin, BuiltinPermissions};
+use compiler::module_cache::ModuleCache;
+use errors::ErrorReport;
+use shared::SpwnSource;
+use spwn::compiler::{compile_spwn, CompileOptions};
+use spwn::leveldata::canonical_level;
+
+// scripts in test/ that don't build, so they have no snapshot. the test fails when one of them
+// starts building, so it can be taken off the list
+const UNBUILDABLE_SCRIPTS: &[(&str, &str)] = &[
+    ("bf/bf_interpreter.spwn", "old syntax"),
+    ("bf/bfold.spwn", "old syntax"),
+    ("bf/brainfugd_pro.spwn", "old syntax"),
+    ("bf/brainfugd_video_example.spwn", "old syntax"),
+    ("bf/text_display.spwn", "old syntax"),
+    ("bf/text_display_pro.spwn", "old syntax"),
+    ("binaryCalculator.spwn", "changes a variable from another trigger function"),
+    ("inclrange.spwn", "uses a step in an inclusive range"),
+    ("level_reading/draw.spwn", "uses an old version of the std"),
+    ("level_reading/main.spwn", "uses an old version of the std"),
+    ("orthographic_rotation/full_cube.spwn", "old syntax"),
+    ("orthographic_rotation/index.spwn", "old syntax"),
+    ("orthographic_rotation/reference_grid.spwn", "old syntax"),
+    ("orthographic_rotation/voxel.spwn", "uses an old version of the std"),
+    ("pckp/main.spwn", "needs its dependencies to be installed"),
+    ("pckup.spwn", "reads a file that doesn't exist"),
+    ("set/graphs.spwn", "uses an old version of the std"),
+    ("set/tile.spwn", "uses an old version of the std"),
+    ("test2.spwn", "old syntax"),
+];
+
+// where a script is built from, when the working directory matters to it
+enum WorkingDir {
+    // the folder the script is in
+    Script,
+    // a copy of the repository root with the scripts next to it, for scripts that change files
+    TempCopy,
+}
+
+// scripts that use the file builtins, with where they are built from and the builtins they
+// are allowed to use (the ones in the command at the top of the script)
+const FILE_SCRIPTS: &[(&str, WorkingDir, &[&str])] = &[
+    (
+        "path_test.spwn",
+        WorkingDir::TempCopy,
+        &[
+            "filekind",
+            "readdir",
+            "fileexists",
+            "readfile",
+            "writefile",
+            "metadata",
+            "deletefile",
+            "mkdir",
+            "rmdir",
+        ],
+    ),
+    ("readfiletest.spwn", WorkingDir::Script, &["readfile"]),
+];
+
+fn snapshot(path: &Path, root: &Path, permissions: BuiltinPermissions) -> Result<String, String> {
+    let code = fs::read_to_string(path).unwrap();
+    let source = SpwnSource::File(path.to_path_buf());
+    let error = |e: ErrorReport| e.message;
+
+    let (statements, notes) = spwn::parse_spwn(code, source.clone(), spwn::builtins::BUILTIN_NAMES)
+        .map_err(|e| error(ErrorReport::from(e)))?;
+    let mut std_out = Vec::<u8>::new();
+    let mut module_cache = ModuleCache::default();
+    let compiled = compile_spwn(
+        statements,
+        source,
+        vec![root.to_path_buf()],
+        notes,
+        permissions,
+        String::new(),
+        &mut std_out,
+        &mut module_cache,
+        CompileOptions {
+            seed: Some(0),
+            quiet: true,
+            ..Default::default()
+        },
+    )
+    .map_err(|e| error(ErrorReport::from(e)))?;
+
+    let mut func_ids = compiled.func_ids;
+    if func_ids.iter().any(|x| !x.obj_list.is_empty()) {
+        let reserved = optimizer::ReservedIds::from_objects(&compiled.objects, &func_ids);
+        func_ids = optimizer::optimize::optimize(func_ids, compiled.closed_groups, reserved);
+    }
+    let level = canonical_level(
+        &func_ids,
+        &compiled.objects,
+        &compiled.obj_prop_names,
+        &compiled.sync_ids,
+    )?;
+
+    let mut out = String::new();
+    let printed = String::from_utf8_lossy(&std_out);
+    if !printed.is_empty() {
+        out += &format!("printed:\n{}\n", printed.trim_end());
+    }
+    out += &format!("level: {} objects\n", level.len());
+    for line in &level {
+        out += &format!("    {}\n", line);
+    }
+    Ok(out)
+}
+
+fn scripts(dir: &Path, found: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            scripts(&path, found);
+        } else if path.extension().and_then(|e| e.to_str()) == Some("spwn") {
+            found.push(path);
+        }
+    }
+}
+
+// builds a script that uses the file builtins from the directory it expects, given by its
+// path relative to the test folder
+fn file_snapshot(
+    script: &Path,
+    working_dir: &WorkingDir,
+    allowed: &[&str],
+    root: &Path,
+) -> Result<String, String> {
+    let mut permissions = BuiltinPermissions::new();
+    for name in allowed {
+        permissions.set(name.parse::<Builtin>().unwrap(), true);
+    }
+    let test_dir = root.join("test");
+    let (dir, path) = match working_dir {
+        WorkingDir::Script => (
+            test_dir.join(script.parent().unwrap()),
+            PathBuf::from(script.file_name().unwrap()),
+        ),
+        WorkingDir::TempCopy => {
+            let dir = std::env::temp_dir().join("spwn_snapshots");
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("test")).unwrap();
+            fs::copy(root.join("README.md"), dir.join("README.md")).unwrap();
+            for entry in fs::read_dir(&test_dir).unwrap() {
+                let file = entry.unwrap().path();
+                if file.is_file() {
+                    fs::copy(&file, dir.join("test").join(file.file_name().unwrap())).unwrap();
+                }
+            }
+            (dir, Path::new("test").join(script))
+        }
+    };
+    let previous = std::env::current_dir().unwrap();
+    std::env::set_current_dir(&dir).unwrap();
+    let result = snapshot(&path, root, permissions);
+    std::env::set_current_dir(previous).unwrap();
+    result
+}
+
+#[test]
+fn snapshots() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let test_dir = root.join("test");
+    let mut paths = Vec::new();
+    scripts(&test_dir, &mut paths);
+    paths.sort();
+    let bless = std::env::var_os("SPWN_BLESS").is_some();
+
+    let file_script = |path: &Path| {
+        let script = path.strip_prefix(&test_dir).unwrap();
+        FILE_SCRIPTS.iter().find(|(s, _, _)| Path::new(s) == script)
+    };
+
+    // the other scripts are built at the same time, some of them take a while
+    let mut results = std::thread::scope(|s| {
+        let handles = paths
+            .iter()
+            .filter(|path| file_script(path).is_none())
+            .map(|path| {
+                let handle = std::thread::Builder::new()
+                    .stack_size(64 * 1024 * 1024)
+                    .spawn_scoped(s, || snapshot(path, root, BuiltinPermissions::new()))
+                    .unwrap();
+                (path.clone(), handle)
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|(path, h)| {
+                let result = h.join().unwrap_or_else(|e| {
+                    let message = e
+                        .downcast_ref::<&str>()
+                        .map(|s| s.to_string())
+                        .or_else(|| e.downcast_ref::<String>().cloned())
+                        .unwrap_or_default();
+                    Err(format!("panicked: {}", message))
+                });
+                (path, result)
+            })
+            .collect::<Vec<_>>()
+    });
+    // these change the working directory, so they are built one at a time
+    for path in &paths {
+        if let Some((script, working_dir, allowed)) = file_script(path) {
+            let result = file_snapshot(Path::new(script), working_dir, allowed, root);
+            results.push((path.clone(), result));
+        }
+    }
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut mismatched = Vec::new();
+    for (path, found) in results {
+        let found = found.map(|out| out.replace(&root.display().to_string(), "<root>"));
+        let script = path.strip_prefix(&test_dir).unwrap();
+        let relative = script.with_extension("txt");
+        let snapshot_path = test_dir.join("snapshots").join(&relative);
+        let unbuildable = UNBUILDABLE_SCRIPTS
+            .iter()
+            .any(|(s, _)| Path::new(s) == script);
+        let found = match (found, unbuildable) {
+            (Ok(found), false) => found,
+            (Err(_), true) => continue,
+            (Ok(_), true) => {
+                eprintln!(
+                    "{} builds now, take it off UNBUILDABLE_SCRIPTS",
+                    script.display()
+                );
+                mismatched.push(relative);
+                continue;
+            }
+            (Err(e), false) => {
+                eprintln!("{} failed to build: {}", script.display(), e);
+                mismatched.push(relative);
+                continue;
+            }
+        };
+        if bless {
+            fs::create_dir_all(snapshot_path.parent().unwrap()).unwrap();
+            fs::write(&snapshot_path, found).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&snapshot_path) {
+            Ok(expected) if expected == found => (),
+            Ok(expected) => {
+                let line = expected
+                    .lines()
+                    .zip(found.lines())
+                    .position(|(a, b)| a != b)
+                    .unwrap_or_else(|| expected.lines().count().min(found.lines().count()));
+                eprintln!(
+                    "{} changed, first at line {}:\n  expected: {}\n  found:    {}",
+                    relative.display(),
+                    line + 1,
+                    expected.lines().nth(line).unwrap_or("<end>"),
+                    found.lines().nth(line).unwrap_or("<end>"),
+                );
+                mismatched.push(relative);
+            }
+            Err(_) => {
+                eprintln!("{} has no snapshot", relative.display());
+                mismatched.push(relative);
+            }
+        }
+    }
+    assert!(
+        mismatched.is_empty(),
+        "{} snapshots are different, run with SPWN_BLESS=1 if that is intended",
+        mismatched.len()
+    );
+}